@@ -0,0 +1,291 @@
+//! Drawing surfaces that rendering plans emit to.
+//!
+//! Layout only produces paths, rectangles and glyph runs through the [`MathCanvas`] trait, so that
+//! other compositors or output formats can be plugged in. The tiny-skia [`Pixmap`] is the default
+//! backend.
+
+use parley::Font;
+use skrifa::{
+    FontRef, GlyphId, MetadataProvider,
+    instance::{LocationRef, NormalizedCoord, Size},
+    outline::{DrawSettings, OutlinePen},
+};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::from_rgba8(0, 0, 0, 255);
+    pub const WHITE: Color = Color::from_rgba8(255, 255, 255, 255);
+
+    pub const fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::BLACK
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CurveTo(f32, f32, f32, f32, f32, f32),
+    Close,
+}
+
+/// Backend independent vector path, with y pointing down
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.commands.push(PathCommand::MoveTo(x, y));
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        self.commands.push(PathCommand::LineTo(x, y));
+    }
+
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) {
+        self.commands.push(PathCommand::QuadTo(cx, cy, x, y));
+    }
+
+    pub fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.commands
+            .push(PathCommand::CurveTo(cx0, cy0, cx1, cy1, x, y));
+    }
+
+    pub fn close(&mut self) {
+        self.commands.push(PathCommand::Close);
+    }
+
+    /// Copy of the path with its points scaled, then translated
+    pub fn transformed(&self, scale_x: f32, scale_y: f32, dx: f32, dy: f32) -> Path {
+        let x = |x: f32| x * scale_x + dx;
+        let y = |y: f32| y * scale_y + dy;
+        let commands = self
+            .commands
+            .iter()
+            .map(|command| match *command {
+                PathCommand::MoveTo(x0, y0) => PathCommand::MoveTo(x(x0), y(y0)),
+                PathCommand::LineTo(x0, y0) => PathCommand::LineTo(x(x0), y(y0)),
+                PathCommand::QuadTo(cx, cy, x0, y0) => {
+                    PathCommand::QuadTo(x(cx), y(cy), x(x0), y(y0))
+                }
+                PathCommand::CurveTo(cx0, cy0, cx1, cy1, x0, y0) => {
+                    PathCommand::CurveTo(x(cx0), y(cy0), x(cx1), y(cy1), x(x0), y(y0))
+                }
+                PathCommand::Close => PathCommand::Close,
+            })
+            .collect();
+        Path { commands }
+    }
+}
+
+/// Glyph positioned relative to the origin of its [`GlyphRun`], on the baseline
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Sequence of glyphs from a single font, size and color
+#[derive(Clone)]
+pub struct GlyphRun {
+    pub font: Font,
+    pub font_size: f32,
+    pub normalized_coords: Vec<NormalizedCoord>,
+    pub glyphs: Vec<PositionedGlyph>,
+    pub color: Color,
+}
+
+impl GlyphRun {
+    /// Outline of a glyph of this run as a path, with the run's origin at (x, y)
+    pub fn glyph_path(&self, glyph: &PositionedGlyph, x: f32, y: f32) -> Option<Path> {
+        let font_ref = FontRef::from_index(self.font.data.as_ref(), self.font.index).ok()?;
+        let outline = font_ref.outline_glyphs().get(GlyphId::from(glyph.id))?;
+        let mut pen = PathPen {
+            path: Path::new(),
+            x: x + glyph.x,
+            y: y + glyph.y,
+        };
+        let location_ref = LocationRef::new(&self.normalized_coords);
+        let settings = DrawSettings::unhinted(Size::new(self.font_size), location_ref);
+        outline.draw(settings, &mut pen).ok()?;
+        Some(pen.path)
+    }
+}
+
+/// Converts glyph outlines (y pointing up) into a [`Path`] at a given origin
+struct PathPen {
+    path: Path,
+    x: f32,
+    y: f32,
+}
+
+impl OutlinePen for PathPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.path.move_to(self.x + x, self.y - y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.path.line_to(self.x + x, self.y - y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.path
+            .quad_to(self.x + cx0, self.y - cy0, self.x + x, self.y - y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.path.curve_to(
+            self.x + cx0,
+            self.y - cy0,
+            self.x + cx1,
+            self.y - cy1,
+            self.x + x,
+            self.y - y,
+        );
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+/// Surface that rendering plans draw on
+pub trait MathCanvas {
+    fn fill_path(&mut self, path: &Path, color: Color);
+
+    fn stroke_path(&mut self, path: &Path, width: f32, color: Color);
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color);
+
+    /// Draw the glyphs of a run with its origin at (x, y).
+    ///
+    /// By default the glyph outlines are filled as paths.
+    fn draw_glyph_run(&mut self, glyph_run: &GlyphRun, x: f32, y: f32) {
+        for glyph in &glyph_run.glyphs {
+            if let Some(path) = glyph_run.glyph_path(glyph, x, y) {
+                self.fill_path(&path, glyph_run.color);
+            }
+        }
+    }
+}
+
+/// Format a coordinate with at most 2 decimals and without exponent, for text based backends
+pub(crate) fn format_number(value: f32) -> String {
+    let value = (value * 100.0).round() / 100.0;
+    // avoid "-0"
+    if value == 0.0 {
+        return "0".into();
+    }
+    format!("{value}")
+}
+
+fn tiny_skia_paint(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(color.r, color.g, color.b, color.a);
+    paint.anti_alias = true;
+    paint
+}
+
+fn tiny_skia_path(path: &Path) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for command in path.commands() {
+        match *command {
+            PathCommand::MoveTo(x, y) => builder.move_to(x, y),
+            PathCommand::LineTo(x, y) => builder.line_to(x, y),
+            PathCommand::QuadTo(cx, cy, x, y) => builder.quad_to(cx, cy, x, y),
+            PathCommand::CurveTo(cx0, cy0, cx1, cy1, x, y) => {
+                builder.cubic_to(cx0, cy0, cx1, cy1, x, y);
+            }
+            PathCommand::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+impl MathCanvas for Pixmap {
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        if let Some(path) = tiny_skia_path(path) {
+            Pixmap::fill_path(
+                self,
+                &path,
+                &tiny_skia_paint(color),
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+
+    fn stroke_path(&mut self, path: &Path, width: f32, color: Color) {
+        let stroke = Stroke {
+            width,
+            ..Default::default()
+        };
+        if let Some(path) = tiny_skia_path(path) {
+            Pixmap::stroke_path(
+                self,
+                &path,
+                &tiny_skia_paint(color),
+                &stroke,
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        // Degenerate rectangles (e.g. the bar of an empty fraction) have nothing to draw
+        if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+            Pixmap::fill_rect(
+                self,
+                rect,
+                &tiny_skia_paint(color),
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_formatting() {
+        assert_eq!(format_number(1.0), "1");
+        assert_eq!(format_number(-0.001), "0");
+        assert_eq!(format_number(2.345_678), "2.35");
+        assert_eq!(format_number(1e10), "10000000000");
+    }
+}
@@ -1,12 +1,19 @@
-#![expect(
-    clippy::cast_possible_truncation,
-    clippy::shadow_unrelated,
-    reason = "Deferred"
-)]
-#![allow(dead_code)]
-#![allow(unused)]
+#![expect(clippy::cast_possible_truncation, reason = "Deferred")]
 #![allow(soft_unstable)]
 #![feature(test)]
+pub mod canvas;
+pub mod math_table;
+pub mod math_variant;
 pub mod mml_types;
+pub mod operator_dictionary;
+pub mod parser;
+pub mod pdf;
 pub mod render;
+pub mod stretchy;
+pub mod style;
+pub mod svg;
+pub mod table;
 pub mod text_rendering;
+
+#[cfg(test)]
+mod test_fonts;
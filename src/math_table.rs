@@ -0,0 +1,612 @@
+//! Reading of the OpenType MATH table, which provides the layout parameters of math fonts.
+//!
+//! The table is not (yet) exposed by skrifa, so it is parsed here directly from the raw table data.
+//! See <https://learn.microsoft.com/en-us/typography/opentype/spec/math>
+
+use std::collections::HashMap;
+
+use skrifa::FontRef;
+use skrifa::raw::{FontData, ReadError, TableProvider, types::Tag};
+
+const MATH: Tag = Tag::new(b"MATH");
+
+/// Global layout constants of a math font, all lengths are in ems and percentages are ratios.
+///
+/// Multiply the lengths by the font size to get pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MathConstants {
+    pub script_percent_scale_down: f32,
+    pub script_script_percent_scale_down: f32,
+    pub delimited_sub_formula_min_height: f32,
+    pub display_operator_min_height: f32,
+    pub math_leading: f32,
+    pub axis_height: f32,
+    pub accent_base_height: f32,
+    pub flattened_accent_base_height: f32,
+    pub subscript_shift_down: f32,
+    pub subscript_top_max: f32,
+    pub subscript_baseline_drop_min: f32,
+    pub superscript_shift_up: f32,
+    pub superscript_shift_up_cramped: f32,
+    pub superscript_bottom_min: f32,
+    pub superscript_baseline_drop_max: f32,
+    pub sub_superscript_gap_min: f32,
+    pub superscript_bottom_max_with_subscript: f32,
+    pub space_after_script: f32,
+    pub upper_limit_gap_min: f32,
+    pub upper_limit_baseline_rise_min: f32,
+    pub lower_limit_gap_min: f32,
+    pub lower_limit_baseline_drop_min: f32,
+    pub stack_top_shift_up: f32,
+    pub stack_top_display_style_shift_up: f32,
+    pub stack_bottom_shift_down: f32,
+    pub stack_bottom_display_style_shift_down: f32,
+    pub stack_gap_min: f32,
+    pub stack_display_style_gap_min: f32,
+    pub stretch_stack_top_shift_up: f32,
+    pub stretch_stack_bottom_shift_down: f32,
+    pub stretch_stack_gap_above_min: f32,
+    pub stretch_stack_gap_below_min: f32,
+    pub fraction_numerator_shift_up: f32,
+    pub fraction_numerator_display_style_shift_up: f32,
+    pub fraction_denominator_shift_down: f32,
+    pub fraction_denominator_display_style_shift_down: f32,
+    pub fraction_numerator_gap_min: f32,
+    pub fraction_num_display_style_gap_min: f32,
+    pub fraction_rule_thickness: f32,
+    pub fraction_denominator_gap_min: f32,
+    pub fraction_denom_display_style_gap_min: f32,
+    pub skewed_fraction_horizontal_gap: f32,
+    pub skewed_fraction_vertical_gap: f32,
+    pub overbar_vertical_gap: f32,
+    pub overbar_rule_thickness: f32,
+    pub overbar_extra_ascender: f32,
+    pub underbar_vertical_gap: f32,
+    pub underbar_rule_thickness: f32,
+    pub underbar_extra_descender: f32,
+    pub radical_vertical_gap: f32,
+    pub radical_display_style_vertical_gap: f32,
+    pub radical_rule_thickness: f32,
+    pub radical_extra_ascender: f32,
+    pub radical_kern_before_degree: f32,
+    pub radical_kern_after_degree: f32,
+    pub radical_degree_bottom_raise_percent: f32,
+}
+
+impl Default for MathConstants {
+    /// Fallback values for fonts without a MATH table, mostly taken from the parameters of TeX's
+    /// Computer Modern fonts.
+    fn default() -> Self {
+        const RULE_THICKNESS: f32 = 0.04;
+        const X_HEIGHT: f32 = 0.431;
+        Self {
+            script_percent_scale_down: 0.71,
+            script_script_percent_scale_down: 0.5041,
+            delimited_sub_formula_min_height: 1.3,
+            display_operator_min_height: 1.3,
+            math_leading: 0.15,
+            axis_height: 0.25,
+            accent_base_height: X_HEIGHT,
+            flattened_accent_base_height: 0.664,
+            subscript_shift_down: 0.15,
+            subscript_top_max: 0.8 * X_HEIGHT,
+            subscript_baseline_drop_min: 0.05,
+            superscript_shift_up: 0.413,
+            superscript_shift_up_cramped: 0.289,
+            superscript_bottom_min: 0.25 * X_HEIGHT,
+            superscript_baseline_drop_max: 0.386,
+            sub_superscript_gap_min: 4.0 * RULE_THICKNESS,
+            superscript_bottom_max_with_subscript: 0.8 * X_HEIGHT,
+            space_after_script: 0.056,
+            upper_limit_gap_min: 0.111,
+            upper_limit_baseline_rise_min: 0.2,
+            lower_limit_gap_min: 0.167,
+            lower_limit_baseline_drop_min: 0.6,
+            stack_top_shift_up: 0.444,
+            stack_top_display_style_shift_up: 0.677,
+            stack_bottom_shift_down: 0.345,
+            stack_bottom_display_style_shift_down: 0.686,
+            stack_gap_min: 3.0 * RULE_THICKNESS,
+            stack_display_style_gap_min: 7.0 * RULE_THICKNESS,
+            stretch_stack_top_shift_up: 0.111,
+            stretch_stack_bottom_shift_down: 0.6,
+            stretch_stack_gap_above_min: 0.2,
+            stretch_stack_gap_below_min: 0.167,
+            fraction_numerator_shift_up: 0.394,
+            fraction_numerator_display_style_shift_up: 0.677,
+            fraction_denominator_shift_down: 0.345,
+            fraction_denominator_display_style_shift_down: 0.686,
+            fraction_numerator_gap_min: RULE_THICKNESS,
+            fraction_num_display_style_gap_min: 3.0 * RULE_THICKNESS,
+            fraction_rule_thickness: RULE_THICKNESS,
+            fraction_denominator_gap_min: RULE_THICKNESS,
+            fraction_denom_display_style_gap_min: 3.0 * RULE_THICKNESS,
+            skewed_fraction_horizontal_gap: 0.35,
+            skewed_fraction_vertical_gap: 0.1,
+            overbar_vertical_gap: 3.0 * RULE_THICKNESS,
+            overbar_rule_thickness: RULE_THICKNESS,
+            overbar_extra_ascender: RULE_THICKNESS,
+            underbar_vertical_gap: 3.0 * RULE_THICKNESS,
+            underbar_rule_thickness: RULE_THICKNESS,
+            underbar_extra_descender: RULE_THICKNESS,
+            radical_vertical_gap: 1.25 * RULE_THICKNESS,
+            radical_display_style_vertical_gap: RULE_THICKNESS + 0.25 * X_HEIGHT,
+            radical_rule_thickness: RULE_THICKNESS,
+            radical_extra_ascender: RULE_THICKNESS,
+            radical_kern_before_degree: 5.0 / 18.0,
+            radical_kern_after_degree: -10.0 / 18.0,
+            radical_degree_bottom_raise_percent: 0.6,
+        }
+    }
+}
+
+impl MathConstants {
+    /// Read the constants from the MATH table of a font, `None` if it doesn't have one.
+    pub fn from_font(font: &FontRef) -> Option<Self> {
+        let units_per_em = f32::from(font.head().ok()?.units_per_em());
+        let math = font.table_data(MATH)?;
+        let offset = math.read_at::<u16>(4).ok()?;
+        let constants = math.split_off(usize::from(offset))?;
+        Self::read(constants, units_per_em).ok()
+    }
+
+    fn read(data: FontData, units_per_em: f32) -> Result<Self, ReadError> {
+        let percent =
+            |offset: usize| Ok::<_, ReadError>(f32::from(data.read_at::<i16>(offset)?) / 100.0);
+        let unsigned = |offset: usize| {
+            Ok::<_, ReadError>(f32::from(data.read_at::<u16>(offset)?) / units_per_em)
+        };
+        // MathValueRecords are a value followed by an offset to a device table, which is ignored
+        let mut record_offset = 8;
+        let mut value = || {
+            let value = f32::from(data.read_at::<i16>(record_offset)?) / units_per_em;
+            record_offset += 4;
+            Ok::<_, ReadError>(value)
+        };
+        Ok(Self {
+            script_percent_scale_down: percent(0)?,
+            script_script_percent_scale_down: percent(2)?,
+            delimited_sub_formula_min_height: unsigned(4)?,
+            display_operator_min_height: unsigned(6)?,
+            math_leading: value()?,
+            axis_height: value()?,
+            accent_base_height: value()?,
+            flattened_accent_base_height: value()?,
+            subscript_shift_down: value()?,
+            subscript_top_max: value()?,
+            subscript_baseline_drop_min: value()?,
+            superscript_shift_up: value()?,
+            superscript_shift_up_cramped: value()?,
+            superscript_bottom_min: value()?,
+            superscript_baseline_drop_max: value()?,
+            sub_superscript_gap_min: value()?,
+            superscript_bottom_max_with_subscript: value()?,
+            space_after_script: value()?,
+            upper_limit_gap_min: value()?,
+            upper_limit_baseline_rise_min: value()?,
+            lower_limit_gap_min: value()?,
+            lower_limit_baseline_drop_min: value()?,
+            stack_top_shift_up: value()?,
+            stack_top_display_style_shift_up: value()?,
+            stack_bottom_shift_down: value()?,
+            stack_bottom_display_style_shift_down: value()?,
+            stack_gap_min: value()?,
+            stack_display_style_gap_min: value()?,
+            stretch_stack_top_shift_up: value()?,
+            stretch_stack_bottom_shift_down: value()?,
+            stretch_stack_gap_above_min: value()?,
+            stretch_stack_gap_below_min: value()?,
+            fraction_numerator_shift_up: value()?,
+            fraction_numerator_display_style_shift_up: value()?,
+            fraction_denominator_shift_down: value()?,
+            fraction_denominator_display_style_shift_down: value()?,
+            fraction_numerator_gap_min: value()?,
+            fraction_num_display_style_gap_min: value()?,
+            fraction_rule_thickness: value()?,
+            fraction_denominator_gap_min: value()?,
+            fraction_denom_display_style_gap_min: value()?,
+            skewed_fraction_horizontal_gap: value()?,
+            skewed_fraction_vertical_gap: value()?,
+            overbar_vertical_gap: value()?,
+            overbar_rule_thickness: value()?,
+            overbar_extra_ascender: value()?,
+            underbar_vertical_gap: value()?,
+            underbar_rule_thickness: value()?,
+            underbar_extra_descender: value()?,
+            radical_vertical_gap: value()?,
+            radical_display_style_vertical_gap: value()?,
+            radical_rule_thickness: value()?,
+            radical_extra_ascender: value()?,
+            radical_kern_before_degree: value()?,
+            radical_kern_after_degree: value()?,
+            radical_degree_bottom_raise_percent: percent(record_offset)?,
+        })
+    }
+}
+
+/// Larger versions of a glyph, and how to assemble it from parts when even larger
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlyphConstruction {
+    /// Pre-drawn variants of increasing size, the first one is usually the glyph itself
+    pub variants: Vec<GlyphVariant>,
+    /// Parts from bottom to top (or left to right), empty if the glyph can't be assembled
+    pub assembly: Vec<GlyphPart>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphVariant {
+    pub glyph_id: u32,
+    /// Size of the variant in the direction of stretching, in ems
+    pub advance: f32,
+}
+
+/// Piece of a glyph assembly, lengths are in ems
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphPart {
+    pub glyph_id: u32,
+    /// Length of the connector at the bottom (or left) which can overlap the previous part
+    pub start_connector_length: f32,
+    /// Length of the connector at the top (or right) which can overlap the next part
+    pub end_connector_length: f32,
+    pub full_advance: f32,
+    /// Extenders can be repeated any number of times (including none)
+    pub extender: bool,
+}
+
+/// Glyph constructions of a math font, used to stretch operators.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MathVariants {
+    /// Minimal overlap between the connectors of consecutive parts of an assembly, in ems
+    pub min_connector_overlap: f32,
+    /// Constructions for stretching vertically, by glyph id
+    pub vertical: HashMap<u32, GlyphConstruction>,
+    /// Constructions for stretching horizontally, by glyph id
+    pub horizontal: HashMap<u32, GlyphConstruction>,
+}
+
+impl MathVariants {
+    /// Read the MathVariants subtable of the MATH table of a font, `None` if it doesn't have one.
+    pub fn from_font(font: &FontRef) -> Option<Self> {
+        let units_per_em = f32::from(font.head().ok()?.units_per_em());
+        let math = font.table_data(MATH)?;
+        let offset = math.read_at::<u16>(8).ok()?;
+        if offset == 0 {
+            return None;
+        }
+        let variants = math.split_off(usize::from(offset))?;
+        Self::read(variants, units_per_em).ok()
+    }
+
+    fn read(data: FontData, units_per_em: f32) -> Result<Self, ReadError> {
+        let min_connector_overlap = f32::from(data.read_at::<u16>(0)?) / units_per_em;
+        let vertical_count = usize::from(data.read_at::<u16>(6)?);
+        let horizontal_count = usize::from(data.read_at::<u16>(8)?);
+        let constructions = |coverage_offset: usize, first_offset: usize, count: usize| {
+            let mut constructions = HashMap::new();
+            // The coverage of a direction without constructions can be NULL
+            let coverage_offset = data.read_at::<u16>(coverage_offset)?;
+            if count == 0 || coverage_offset == 0 {
+                return Ok(constructions);
+            }
+            let coverage = read_coverage(data, coverage_offset)?;
+            // Glyphs without a construction would be past the end of the coverage
+            for (index, glyph_id) in coverage.into_iter().enumerate().take(count) {
+                let offset = data.read_at::<u16>(first_offset + 2 * index)?;
+                let construction = read_construction(data, usize::from(offset), units_per_em)?;
+                constructions.insert(glyph_id, construction);
+            }
+            Ok::<_, ReadError>(constructions)
+        };
+        let vertical = constructions(2, 10, vertical_count)?;
+        let horizontal = constructions(4, 10 + 2 * vertical_count, horizontal_count)?;
+        Ok(Self {
+            min_connector_overlap,
+            vertical,
+            horizontal,
+        })
+    }
+}
+
+/// Corner of a glyph, where a script can be kerned into its shape
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopRight,
+    TopLeft,
+    BottomRight,
+    BottomLeft,
+}
+
+/// Kerning at a corner of a glyph, which depends on the height: e.g. a superscript can move
+/// further left under the overhang of an "f" than next to its stem
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MathKern {
+    /// Heights separating the ranges of the kern values, in ems and in increasing order
+    pub correction_heights: Vec<f32>,
+    /// Kern of each range, one more than the heights, in ems
+    pub kern_values: Vec<f32>,
+}
+
+impl MathKern {
+    /// Kern at a height above the baseline of the glyph, 0 if the glyph has none
+    pub fn kern_at(&self, height: f32) -> f32 {
+        let index = self
+            .correction_heights
+            .iter()
+            .take_while(|&&correction_height| correction_height <= height)
+            .count();
+        self.kern_values.get(index).copied().unwrap_or(0.0)
+    }
+}
+
+/// Kerning of the four corners of a glyph
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MathKernInfo {
+    pub top_right: MathKern,
+    pub top_left: MathKern,
+    pub bottom_right: MathKern,
+    pub bottom_left: MathKern,
+}
+
+/// Metrics of individual glyphs of a math font, used to attach scripts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MathGlyphInfo {
+    /// Italic correction by glyph id in ems: how far the top of a slanted glyph extends past its
+    /// advance
+    pub italics_corrections: HashMap<u32, f32>,
+    /// Kerning of the corners by glyph id
+    pub kerns: HashMap<u32, MathKernInfo>,
+}
+
+impl MathGlyphInfo {
+    /// Read the MathGlyphInfo subtable of the MATH table of a font, `None` if it doesn't have one.
+    pub fn from_font(font: &FontRef) -> Option<Self> {
+        let units_per_em = f32::from(font.head().ok()?.units_per_em());
+        let math = font.table_data(MATH)?;
+        let offset = math.read_at::<u16>(6).ok()?;
+        if offset == 0 {
+            return None;
+        }
+        let glyph_info = math.split_off(usize::from(offset))?;
+        Self::read(glyph_info, units_per_em).ok()
+    }
+
+    /// Italic correction of a glyph in ems, 0 if it has none
+    pub fn italics_correction(&self, glyph_id: u32) -> f32 {
+        self.italics_corrections
+            .get(&glyph_id)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Kern at a corner of a glyph, at a height in ems above its baseline
+    pub fn kern(&self, glyph_id: u32, corner: Corner, height: f32) -> f32 {
+        let Some(kerns) = self.kerns.get(&glyph_id) else {
+            return 0.0;
+        };
+        match corner {
+            Corner::TopRight => kerns.top_right.kern_at(height),
+            Corner::TopLeft => kerns.top_left.kern_at(height),
+            Corner::BottomRight => kerns.bottom_right.kern_at(height),
+            Corner::BottomLeft => kerns.bottom_left.kern_at(height),
+        }
+    }
+
+    fn read(data: FontData, units_per_em: f32) -> Result<Self, ReadError> {
+        let mut italics_corrections = HashMap::new();
+        let italics_offset = usize::from(data.read_at::<u16>(0)?);
+        if italics_offset != 0 {
+            let italics = data
+                .split_off(italics_offset)
+                .ok_or(ReadError::OutOfBounds)?;
+            let count = usize::from(italics.read_at::<u16>(2)?);
+            let coverage = read_coverage(italics, italics.read_at::<u16>(0)?)?;
+            for (index, glyph_id) in coverage.into_iter().enumerate().take(count) {
+                // MathValueRecords, whose device table offsets are ignored
+                let value = italics.read_at::<i16>(4 + 4 * index)?;
+                italics_corrections.insert(glyph_id, f32::from(value) / units_per_em);
+            }
+        }
+
+        let mut kerns = HashMap::new();
+        let kern_info_offset = usize::from(data.read_at::<u16>(6)?);
+        if kern_info_offset != 0 {
+            let kern_info = data
+                .split_off(kern_info_offset)
+                .ok_or(ReadError::OutOfBounds)?;
+            let count = usize::from(kern_info.read_at::<u16>(2)?);
+            let coverage = read_coverage(kern_info, kern_info.read_at::<u16>(0)?)?;
+            for (index, glyph_id) in coverage.into_iter().enumerate().take(count) {
+                // Offsets of the top right, top left, bottom right and bottom left kerns
+                let record = 4 + 8 * index;
+                let kern = |corner: usize| {
+                    let offset = kern_info.read_at::<u16>(record + 2 * corner)?;
+                    read_kern(kern_info, usize::from(offset), units_per_em)
+                };
+                let corners = MathKernInfo {
+                    top_right: kern(0)?,
+                    top_left: kern(1)?,
+                    bottom_right: kern(2)?,
+                    bottom_left: kern(3)?,
+                };
+                kerns.insert(glyph_id, corners);
+            }
+        }
+
+        Ok(Self {
+            italics_corrections,
+            kerns,
+        })
+    }
+}
+
+/// Kern table at an offset, empty if the offset is 0
+fn read_kern(data: FontData, offset: usize, units_per_em: f32) -> Result<MathKern, ReadError> {
+    if offset == 0 {
+        return Ok(MathKern::default());
+    }
+    let data = data.split_off(offset).ok_or(ReadError::OutOfBounds)?;
+    let height_count = usize::from(data.read_at::<u16>(0)?);
+    // MathValueRecords of the heights followed by those of the kerns
+    let value = |index: usize| {
+        Ok::<_, ReadError>(f32::from(data.read_at::<i16>(2 + 4 * index)?) / units_per_em)
+    };
+    Ok(MathKern {
+        correction_heights: (0..height_count).map(value).collect::<Result<_, _>>()?,
+        kern_values: (height_count..2 * height_count + 1)
+            .map(value)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Glyph ids of a coverage table, in coverage index order
+fn read_coverage(data: FontData, offset: u16) -> Result<Vec<u32>, ReadError> {
+    let data = data
+        .split_off(usize::from(offset))
+        .ok_or(ReadError::OutOfBounds)?;
+    let format = data.read_at::<u16>(0)?;
+    let count = usize::from(data.read_at::<u16>(2)?);
+    let mut glyph_ids = vec![];
+    match format {
+        1 => {
+            for index in 0..count {
+                glyph_ids.push(u32::from(data.read_at::<u16>(4 + 2 * index)?));
+            }
+        }
+        2 => {
+            // Ranges are sorted by coverage index, so their glyphs can be appended in turn
+            for index in 0..count {
+                let start = data.read_at::<u16>(4 + 6 * index)?;
+                let end = data.read_at::<u16>(6 + 6 * index)?;
+                glyph_ids.extend((start..=end).map(u32::from));
+            }
+        }
+        _ => return Err(ReadError::InvalidFormat(format.into())),
+    }
+    Ok(glyph_ids)
+}
+
+fn read_construction(
+    data: FontData,
+    offset: usize,
+    units_per_em: f32,
+) -> Result<GlyphConstruction, ReadError> {
+    let data = data.split_off(offset).ok_or(ReadError::OutOfBounds)?;
+    let length =
+        |offset: usize| Ok::<_, ReadError>(f32::from(data.read_at::<u16>(offset)?) / units_per_em);
+    let assembly_offset = usize::from(data.read_at::<u16>(0)?);
+    let variant_count = usize::from(data.read_at::<u16>(2)?);
+    let variants = (0..variant_count)
+        .map(|index| {
+            Ok(GlyphVariant {
+                glyph_id: u32::from(data.read_at::<u16>(4 + 4 * index)?),
+                advance: length(6 + 4 * index)?,
+            })
+        })
+        .collect::<Result<_, ReadError>>()?;
+
+    let mut assembly = vec![];
+    if assembly_offset != 0 {
+        // The italics correction MathValueRecord comes first, then the parts
+        let part_count = usize::from(data.read_at::<u16>(assembly_offset + 4)?);
+        for index in 0..part_count {
+            let part = assembly_offset + 6 + 10 * index;
+            assembly.push(GlyphPart {
+                glyph_id: u32::from(data.read_at::<u16>(part)?),
+                start_connector_length: length(part + 2)?,
+                end_connector_length: length(part + 4)?,
+                full_advance: length(part + 6)?,
+                extender: data.read_at::<u16>(part + 8)? & 1 != 0,
+            });
+        }
+    }
+    Ok(GlyphConstruction { variants, assembly })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::MATH_FONT;
+    use skrifa::MetadataProvider;
+
+    #[test]
+    fn read_constants() {
+        let font = FontRef::new(MATH_FONT).unwrap();
+        let constants = MathConstants::from_font(&font).unwrap();
+        assert!((0.5..0.9).contains(&constants.script_percent_scale_down));
+        assert!(constants.axis_height > 0.0);
+        assert!(constants.fraction_rule_thickness > 0.0);
+        assert!((0.0..1.0).contains(&constants.radical_degree_bottom_raise_percent));
+    }
+
+    #[test]
+    fn read_variants() {
+        let font = FontRef::new(MATH_FONT).unwrap();
+        let variants = MathVariants::from_font(&font).unwrap();
+        let parenthesis = font.charmap().map('(').unwrap().to_u32();
+        let construction = &variants.vertical[&parenthesis];
+        assert!(construction.variants.len() > 1);
+        assert!(
+            construction
+                .variants
+                .windows(2)
+                .all(|pair| pair[0].advance <= pair[1].advance)
+        );
+        assert!(construction.assembly.iter().any(|part| part.extender));
+        let arrow = font.charmap().map('→').unwrap().to_u32();
+        assert!(variants.horizontal.contains_key(&arrow));
+    }
+
+    #[test]
+    fn variants_in_one_direction() {
+        // Two vertical variants of glyph 5, and a NULL horizontal coverage
+        let words: [u16; 15] = [
+            // Overlap, coverages, counts and construction offset
+            100, 12, 0, 1, 0, 18, //
+            // Coverage format 1 with one glyph
+            1, 1, 5, //
+            // No assembly, and two variants with their advances
+            0, 2, 5, 800, 6, 1200,
+        ];
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+        let variants = MathVariants::read(FontData::new(&bytes), 1000.0).unwrap();
+        assert_eq!(variants.min_connector_overlap, 0.1);
+        assert!(variants.horizontal.is_empty());
+        let construction = &variants.vertical[&5];
+        assert_eq!(
+            construction.variants,
+            [
+                GlyphVariant {
+                    glyph_id: 5,
+                    advance: 0.8
+                },
+                GlyphVariant {
+                    glyph_id: 6,
+                    advance: 1.2
+                },
+            ]
+        );
+        assert!(construction.assembly.is_empty());
+    }
+
+    #[test]
+    fn read_glyph_info() {
+        let font = FontRef::new(MATH_FONT).unwrap();
+        let glyph_info = MathGlyphInfo::from_font(&font).unwrap();
+        let integral = font.charmap().map('∫').unwrap().to_u32();
+        let equals = font.charmap().map('=').unwrap().to_u32();
+        assert!(glyph_info.italics_correction(integral) > 0.0);
+        assert_eq!(glyph_info.italics_correction(equals), 0.0);
+    }
+
+    #[test]
+    fn kern_ranges() {
+        let kern = MathKern {
+            correction_heights: vec![0.2, 0.5],
+            kern_values: vec![-0.1, 0.0, 0.05],
+        };
+        assert_eq!(kern.kern_at(0.0), -0.1);
+        assert_eq!(kern.kern_at(0.3), 0.0);
+        assert_eq!(kern.kern_at(0.6), 0.05);
+        assert_eq!(MathKern::default().kern_at(0.3), 0.0);
+    }
+}
@@ -0,0 +1,176 @@
+//! Remapping of letters and digits to the Mathematical Alphanumeric Symbols block, which is how
+//! `mathvariant` styles are drawn: e.g. a bold "x" is the character U+1D431 rather than a bold
+//! font.
+//!
+//! See <https://w3c.github.io/mathml-core/#new-text-transform-mappings>
+
+use crate::mml_types::MathVariant;
+use crate::text_rendering::TextRenderer;
+
+/// Number of Latin letters in each alphabet, capitals followed by small letters
+const LATIN_LETTERS: u32 = 26;
+
+/// Text with each letter and digit replaced by its styled form, other characters (and letters
+/// without a styled form) are kept
+pub fn apply_math_variant(text: &str, variant: MathVariant) -> String {
+    text.chars().map(|c| styled_char(c, variant)).collect()
+}
+
+/// Text styled as by [`apply_math_variant`], but keeping the characters whose styled form is
+/// missing from the math font (e.g. when no math font is installed), as it would not be drawn
+pub fn styled_text(text_renderer: &TextRenderer, text: &str, variant: MathVariant) -> String {
+    text.chars()
+        .map(|c| {
+            let styled = styled_char(c, variant);
+            if styled != c && text_renderer.math_glyph_id(styled).is_none() {
+                c
+            } else {
+                styled
+            }
+        })
+        .collect()
+}
+
+/// The styled form of a character, or the character itself if the variant has none
+pub fn styled_char(c: char, variant: MathVariant) -> char {
+    if let Some(styled) = exception(c, variant) {
+        return styled;
+    }
+    let code_point = match c {
+        'A'..='Z' => latin_start(variant).map(|start| start + (c as u32 - 'A' as u32)),
+        'a'..='z' => {
+            latin_start(variant).map(|start| start + LATIN_LETTERS + (c as u32 - 'a' as u32))
+        }
+        '0'..='9' => digit_start(variant).map(|start| start + (c as u32 - '0' as u32)),
+        _ => greek_index(c).and_then(|index| Some(greek_start(variant)? + index)),
+    };
+    code_point.and_then(char::from_u32).unwrap_or(c)
+}
+
+/// First code point (the capital A) of the Latin alphabet of a variant
+fn latin_start(variant: MathVariant) -> Option<u32> {
+    match variant {
+        MathVariant::Bold => Some(0x1D400),
+        MathVariant::Italic => Some(0x1D434),
+        MathVariant::BoldItalic => Some(0x1D468),
+        MathVariant::Script => Some(0x1D49C),
+        MathVariant::BoldScript => Some(0x1D4D0),
+        MathVariant::Fraktur => Some(0x1D504),
+        MathVariant::DoubleStruck => Some(0x1D538),
+        MathVariant::BoldFraktur => Some(0x1D56C),
+        MathVariant::SansSerif => Some(0x1D5A0),
+        MathVariant::BoldSansSerif => Some(0x1D5D4),
+        MathVariant::SansSerifItalic => Some(0x1D608),
+        MathVariant::SansSerifBoldItalic => Some(0x1D63C),
+        MathVariant::Monospace => Some(0x1D670),
+        // The Arabic variants only apply to Arabic letters, which are not remapped
+        MathVariant::Normal
+        | MathVariant::Initial
+        | MathVariant::Tailed
+        | MathVariant::Looped
+        | MathVariant::Stretched => None,
+    }
+}
+
+/// First code point (the capital alpha) of the Greek alphabet of a variant
+fn greek_start(variant: MathVariant) -> Option<u32> {
+    match variant {
+        MathVariant::Bold => Some(0x1D6A8),
+        MathVariant::Italic => Some(0x1D6E2),
+        MathVariant::BoldItalic => Some(0x1D71C),
+        MathVariant::BoldSansSerif => Some(0x1D756),
+        MathVariant::SansSerifBoldItalic => Some(0x1D790),
+        _ => None,
+    }
+}
+
+/// First code point (the zero) of the digits of a variant
+fn digit_start(variant: MathVariant) -> Option<u32> {
+    match variant {
+        MathVariant::Bold => Some(0x1D7CE),
+        MathVariant::DoubleStruck => Some(0x1D7D8),
+        MathVariant::SansSerif => Some(0x1D7E2),
+        MathVariant::BoldSansSerif => Some(0x1D7EC),
+        MathVariant::Monospace => Some(0x1D7F6),
+        _ => None,
+    }
+}
+
+/// Position of a character in the Greek alphabets: the capitals (with theta symbol in the gap of
+/// U+03A2), nabla, the small letters, then partial differential and the letter variants
+fn greek_index(c: char) -> Option<u32> {
+    match c {
+        '\u{3A2}' => None,
+        'Α'..='Ω' => Some(c as u32 - 'Α' as u32),
+        'ϴ' => Some(17),
+        '∇' => Some(25),
+        'α'..='ω' => Some(26 + c as u32 - 'α' as u32),
+        '∂' => Some(51),
+        'ϵ' => Some(52),
+        'ϑ' => Some(53),
+        'ϰ' => Some(54),
+        'ϕ' => Some(55),
+        'ϱ' => Some(56),
+        'ϖ' => Some(57),
+        _ => None,
+    }
+}
+
+/// Styled letters which were encoded before the block, and are left as holes in it
+fn exception(c: char, variant: MathVariant) -> Option<char> {
+    let styled = match (variant, c) {
+        (MathVariant::Italic, 'h') => 'ℎ',
+        (MathVariant::Italic, 'ı') => '𝚤',
+        (MathVariant::Italic, 'ȷ') => '𝚥',
+        (MathVariant::Script, 'B') => 'ℬ',
+        (MathVariant::Script, 'E') => 'ℰ',
+        (MathVariant::Script, 'F') => 'ℱ',
+        (MathVariant::Script, 'H') => 'ℋ',
+        (MathVariant::Script, 'I') => 'ℐ',
+        (MathVariant::Script, 'L') => 'ℒ',
+        (MathVariant::Script, 'M') => 'ℳ',
+        (MathVariant::Script, 'R') => 'ℛ',
+        (MathVariant::Script, 'e') => 'ℯ',
+        (MathVariant::Script, 'g') => 'ℊ',
+        (MathVariant::Script, 'o') => 'ℴ',
+        (MathVariant::Fraktur, 'C') => 'ℭ',
+        (MathVariant::Fraktur, 'H') => 'ℌ',
+        (MathVariant::Fraktur, 'I') => 'ℑ',
+        (MathVariant::Fraktur, 'R') => 'ℜ',
+        (MathVariant::Fraktur, 'Z') => 'ℨ',
+        (MathVariant::DoubleStruck, 'C') => 'ℂ',
+        (MathVariant::DoubleStruck, 'H') => 'ℍ',
+        (MathVariant::DoubleStruck, 'N') => 'ℕ',
+        (MathVariant::DoubleStruck, 'P') => 'ℙ',
+        (MathVariant::DoubleStruck, 'Q') => 'ℚ',
+        (MathVariant::DoubleStruck, 'R') => 'ℝ',
+        (MathVariant::DoubleStruck, 'Z') => 'ℤ',
+        _ => return None,
+    };
+    Some(styled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin_and_digits() {
+        assert_eq!(apply_math_variant("x2", MathVariant::Bold), "𝐱𝟐");
+        assert_eq!(apply_math_variant("xh", MathVariant::Italic), "𝑥ℎ");
+        assert_eq!(apply_math_variant("RZ1", MathVariant::DoubleStruck), "ℝℤ𝟙");
+        assert_eq!(apply_math_variant("ABz", MathVariant::Script), "𝒜ℬ𝓏");
+        assert_eq!(apply_math_variant("gH", MathVariant::Fraktur), "𝔤ℌ");
+        assert_eq!(apply_math_variant("az09", MathVariant::Monospace), "𝚊𝚣𝟶𝟿");
+        assert_eq!(apply_math_variant("x+1", MathVariant::Normal), "x+1");
+    }
+
+    #[test]
+    fn greek() {
+        assert_eq!(apply_math_variant("αω", MathVariant::Italic), "𝛼𝜔");
+        assert_eq!(apply_math_variant("ΑΩ∇", MathVariant::Bold), "𝚨𝛀𝛁");
+        assert_eq!(apply_math_variant("ϴϖ∂", MathVariant::BoldItalic), "𝜭𝝕𝝏");
+        // There is no double-struck Greek in the block
+        assert_eq!(apply_math_variant("π", MathVariant::DoubleStruck), "π");
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::canvas::Color;
+
 /// The root element of a MathML document
 pub struct Math {
     pub attributes: HashMap<String, String>,
@@ -12,14 +14,103 @@ pub struct Mrow {
 pub struct Mphantom {
     pub terms: Vec<Element>,
 }
+/// Blank space, the dimensions are 0 when `None`
+#[derive(Default)]
+pub struct Mspace {
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    pub depth: Option<Length>,
+}
+/// Dimension of the content of an `<mpadded>`, which its attributes can refer to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PseudoUnit {
+    Width,
+    Height,
+    Depth,
+}
+/// Value of an `<mpadded>` attribute
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaddedValue {
+    /// Length, whose percentages are of the dimension set by the attribute
+    Length(Length),
+    /// Multiple of a dimension of the content, e.g. `0.5height`
+    Dimension(f32, PseudoUnit),
+}
+/// Attribute of an `<mpadded>`, which either sets a dimension or changes it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PaddedLength {
+    pub value: PaddedValue,
+    /// Whether the value is added to the dimension (from a `+` or `-` sign) instead of replacing
+    /// it, except for percentages which always give the new dimension
+    pub relative: bool,
+}
+/// Content with changed dimensions, the attributes are `None` to keep those of the content
+pub struct Mpadded {
+    pub content: Box<Element>,
+    pub width: Option<PaddedLength>,
+    pub height: Option<PaddedLength>,
+    pub depth: Option<PaddedLength>,
+    /// Horizontal shift of the content
+    pub lspace: Option<PaddedLength>,
+    /// Vertical shift of the content, upwards
+    pub voffset: Option<PaddedLength>,
+}
+/// Identifier, italic when it is a single character unless `mathvariant` is given
 pub struct Mi {
     pub identifier: String,
+    pub mathvariant: Option<MathVariant>,
 }
 pub struct Mn {
     pub number: String,
+    pub mathvariant: Option<MathVariant>,
+}
+/// Position of an operator relative to its operands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Form {
+    Prefix,
+    Infix,
+    Postfix,
+}
+
+/// Length given in an attribute, resolved when rendering. Absolute units (pt, in, cm, mm) are
+/// converted to pixels when parsing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Em(f32),
+    Ex(f32),
+    Px(f32),
+    /// Percentage of the font size, except where an attribute refers to another length
+    Percent(f32),
 }
+
+impl Length {
+    /// Length in pixels, with a font size and x-height in pixels
+    pub fn to_px(self, font_size: f32, x_height: f32) -> f32 {
+        match self {
+            Length::Em(length) => length * font_size,
+            Length::Ex(length) => length * x_height,
+            Length::Px(length) => length,
+            Length::Percent(percentage) => percentage / 100.0 * font_size,
+        }
+    }
+}
+
+/// Operator, its attributes are `None` when not specified, to use the operator dictionary
+#[derive(Default)]
 pub struct Mo {
     pub operator: String,
+    /// Inferred from the position of the operator in its `<mrow>` if not specified
+    pub form: Option<Form>,
+    pub lspace: Option<Length>,
+    pub rspace: Option<Length>,
+    /// Whether the operator stretches to the size of its siblings
+    pub stretchy: Option<bool>,
+    pub symmetric: Option<bool>,
+    pub largeop: Option<bool>,
+    pub movablelimits: Option<bool>,
+    pub fence: Option<bool>,
+    pub separator: Option<bool>,
+    pub mathvariant: Option<MathVariant>,
 }
 pub struct Msub {
     pub base: Box<Element>,
@@ -29,12 +120,51 @@ pub struct Msup {
     pub base: Box<Element>,
     pub superscript: Box<Element>,
 }
+pub struct Msubsup {
+    pub base: Box<Element>,
+    pub subscript: Box<Element>,
+    pub superscript: Box<Element>,
+}
+pub struct Munder {
+    pub base: Box<Element>,
+    pub underscript: Box<Element>,
+    /// Whether the underscript is an accent, drawn at the size of the base and close to it
+    pub accentunder: Option<bool>,
+}
+pub struct Mover {
+    pub base: Box<Element>,
+    pub overscript: Box<Element>,
+    /// Whether the overscript is an accent, drawn at the size of the base and close to it
+    pub accent: Option<bool>,
+}
+pub struct Munderover {
+    pub base: Box<Element>,
+    pub underscript: Box<Element>,
+    pub overscript: Box<Element>,
+    pub accentunder: Option<bool>,
+    pub accent: Option<bool>,
+}
+/// Thickness of the bar of a fraction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineThickness {
+    Length(Length),
+    /// Multiple of the default thickness of the font, e.g. from `thick` or a percentage
+    Relative(f32),
+}
 pub struct Mfrac {
     pub numerator: Box<Element>,
     pub denominator: Box<Element>,
+    /// Thickness of the bar, the default of the font when `None`, and no bar when zero (e.g. for
+    /// binomial coefficients)
+    pub linethickness: Option<LineThickness>,
+    pub numalign: ColumnAlign,
+    pub denomalign: ColumnAlign,
+    /// Whether the numerator and denominator are side by side, separated by a slash
+    pub bevelled: bool,
 }
 pub struct Mtext {
     pub text: String,
+    pub mathvariant: Option<MathVariant>,
 }
 // Mspace,
 // Ms,
@@ -45,6 +175,139 @@ pub struct Mroot {
     pub base: Box<Element>,
     pub index: Option<Box<Element>>,
 }
+/// Style of the letters of tokens, from the `mathvariant` attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathVariant {
+    Normal,
+    Bold,
+    Italic,
+    BoldItalic,
+    DoubleStruck,
+    BoldFraktur,
+    Script,
+    BoldScript,
+    Fraktur,
+    SansSerif,
+    BoldSansSerif,
+    SansSerifItalic,
+    SansSerifBoldItalic,
+    Monospace,
+    Initial,
+    Tailed,
+    Looped,
+    Stretched,
+}
+/// Change of the `scriptlevel`, either relative to the inherited one (`+1`, `-1`) or absolute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptLevel {
+    Relative(i32),
+    Absolute(i32),
+}
+/// Style of its content, the attributes are `None` when they are inherited
+pub struct Mstyle {
+    pub content: Box<Element>,
+    pub displaystyle: Option<bool>,
+    pub scriptlevel: Option<ScriptLevel>,
+    /// Smallest font size that an increase of `scriptlevel` scales down to
+    pub scriptminsize: Option<Length>,
+    /// Font size, relative to the inherited one when in em
+    pub mathsize: Option<Length>,
+    pub mathcolor: Option<Color>,
+    pub mathbackground: Option<Color>,
+    pub mathvariant: Option<MathVariant>,
+}
+/// Notation drawn by `<menclose>` around or over its content
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Notation {
+    Box,
+    RoundedBox,
+    Circle,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    UpDiagonalStrike,
+    DownDiagonalStrike,
+    HorizontalStrike,
+    VerticalStrike,
+    /// Lines on the top and right
+    Actuarial,
+    /// Lines on the right and bottom, as used in arabic notation
+    Madruwb,
+    /// Long division sign, which is the default notation
+    Longdiv,
+    /// Radical sign, as for `<msqrt>`
+    Radical,
+}
+pub struct Menclose {
+    pub notation: Vec<Notation>,
+    pub content: Box<Element>,
+}
+/// Subscript and superscript of an `<mmultiscripts>`, either of them may be missing (`<none/>`)
+pub struct ScriptPair {
+    pub subscript: Option<Element>,
+    pub superscript: Option<Element>,
+}
+/// Base with any number of pairs of scripts after it, and before it (after `<mprescripts/>`)
+pub struct Mmultiscripts {
+    pub base: Box<Element>,
+    pub postscripts: Vec<ScriptPair>,
+    pub prescripts: Vec<ScriptPair>,
+}
+/// Horizontal alignment of the cells in a column
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+/// Vertical alignment of the cells in a row
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RowAlign {
+    Top,
+    Bottom,
+    Center,
+    #[default]
+    Baseline,
+    Axis,
+}
+/// Lines around a table or between its rows and columns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineStyle {
+    #[default]
+    None,
+    Solid,
+    Dashed,
+}
+/// Table, the lists of attribute values apply to successive rows or columns, the last value being
+/// repeated for the remaining ones, and are empty when not specified
+#[derive(Default)]
+pub struct Mtable {
+    pub rows: Vec<Mtr>,
+    pub columnalign: Vec<ColumnAlign>,
+    pub rowalign: Vec<RowAlign>,
+    pub columnspacing: Vec<Length>,
+    pub rowspacing: Vec<Length>,
+    pub columnlines: Vec<LineStyle>,
+    pub rowlines: Vec<LineStyle>,
+    pub frame: LineStyle,
+}
+/// Row of a table, its alignments override those of the table
+#[derive(Default)]
+pub struct Mtr {
+    pub cells: Vec<Mtd>,
+    pub columnalign: Vec<ColumnAlign>,
+    pub rowalign: Option<RowAlign>,
+}
+/// Cell of a table, its alignments override those of the row and table
+pub struct Mtd {
+    pub content: Box<Element>,
+    pub rowspan: usize,
+    pub columnspan: usize,
+    pub columnalign: Option<ColumnAlign>,
+    pub rowalign: Option<RowAlign>,
+}
 /// Types of presentation elements
 pub enum Element {
     Mrow(Mrow),
@@ -55,24 +318,23 @@ pub enum Element {
     Msup(Msup),
     Mfrac(Mfrac),
     Mtext(Mtext),
-    // Mspace,
+    Mspace(Mspace),
     // Ms,
     Msqrt(Msqrt),
     Mroot(Mroot),
-    // Mstyle,
+    Mstyle(Mstyle),
     // Merror,
-    // Mpadded,
+    Mpadded(Mpadded),
     Mphantom(Mphantom),
     // Mfenced,
-    // Menclose,
-    // Msubsup,
-    // Munder,
-    // Mover,
-    // Munderover,
-    // Mmultiscripts,
-    // Mtable,
-    // Mtr,
-    // Mtd,
+    Menclose(Menclose),
+    Msubsup(Msubsup),
+    Munder(Munder),
+    Mover(Mover),
+    Munderover(Munderover),
+    Mmultiscripts(Mmultiscripts),
+    /// Rows and cells only exist within tables
+    Mtable(Mtable),
     // Maligngroup,
     // Malignmark,
     // Mglyph,
@@ -87,15 +349,33 @@ pub fn mphantom(terms: Vec<Element>) -> Element {
 }
 pub fn mi(identifier: &str) -> Element {
     let identifier = identifier.into();
-    Element::Mi(Mi { identifier })
+    Element::Mi(Mi {
+        identifier,
+        mathvariant: None,
+    })
 }
 pub fn mo(operator: &str) -> Element {
     let operator = operator.into();
-    Element::Mo(Mo { operator })
+    Element::Mo(Mo {
+        operator,
+        ..Default::default()
+    })
+}
+/// Operator stretching to the size of its siblings in an `<mrow>`, e.g. parentheses
+pub fn stretchy_mo(operator: &str) -> Element {
+    let operator = operator.into();
+    Element::Mo(Mo {
+        operator,
+        stretchy: Some(true),
+        ..Default::default()
+    })
 }
 pub fn mn(number: &str) -> Element {
     let number = number.into();
-    Element::Mn(Mn { number })
+    Element::Mn(Mn {
+        number,
+        mathvariant: None,
+    })
 }
 pub fn msup(base: Element, superscript: Element) -> Element {
     let base = Box::new(base);
@@ -107,17 +387,135 @@ pub fn msub(base: Element, subscript: Element) -> Element {
     let subscript = subscript.into();
     Element::Msub(Msub { base, subscript })
 }
+pub fn msubsup(base: Element, subscript: Element, superscript: Element) -> Element {
+    let base = base.into();
+    let subscript = subscript.into();
+    let superscript = superscript.into();
+    Element::Msubsup(Msubsup {
+        base,
+        subscript,
+        superscript,
+    })
+}
+pub fn munder(base: Element, underscript: Element) -> Element {
+    let base = base.into();
+    let underscript = underscript.into();
+    Element::Munder(Munder {
+        base,
+        underscript,
+        accentunder: None,
+    })
+}
+pub fn mover(base: Element, overscript: Element) -> Element {
+    let base = base.into();
+    let overscript = overscript.into();
+    Element::Mover(Mover {
+        base,
+        overscript,
+        accent: None,
+    })
+}
+pub fn munderover(base: Element, underscript: Element, overscript: Element) -> Element {
+    let base = base.into();
+    let underscript = underscript.into();
+    let overscript = overscript.into();
+    Element::Munderover(Munderover {
+        base,
+        underscript,
+        overscript,
+        accentunder: None,
+        accent: None,
+    })
+}
+/// Style without any attribute, to be set on the returned value
+pub fn mstyle(content: Element) -> Element {
+    Element::Mstyle(Mstyle {
+        content: content.into(),
+        displaystyle: None,
+        scriptlevel: None,
+        scriptminsize: None,
+        mathsize: None,
+        mathcolor: None,
+        mathbackground: None,
+        mathvariant: None,
+    })
+}
+pub fn mpadded(content: Element) -> Element {
+    Element::Mpadded(Mpadded {
+        content: content.into(),
+        width: None,
+        height: None,
+        depth: None,
+        lspace: None,
+        voffset: None,
+    })
+}
+pub fn menclose(notation: Vec<Notation>, content: Element) -> Element {
+    let content = content.into();
+    Element::Menclose(Menclose { notation, content })
+}
+/// Pairs are given as (subscript, superscript)
+pub fn mmultiscripts(
+    base: Element,
+    postscripts: Vec<(Option<Element>, Option<Element>)>,
+    prescripts: Vec<(Option<Element>, Option<Element>)>,
+) -> Element {
+    let pairs = |pairs: Vec<(Option<Element>, Option<Element>)>| {
+        pairs
+            .into_iter()
+            .map(|(subscript, superscript)| ScriptPair {
+                subscript,
+                superscript,
+            })
+            .collect()
+    };
+    Element::Mmultiscripts(Mmultiscripts {
+        base: base.into(),
+        postscripts: pairs(postscripts),
+        prescripts: pairs(prescripts),
+    })
+}
+/// Table whose cells hold the given elements, with default attributes
+pub fn mtable(rows: Vec<Vec<Element>>) -> Element {
+    let rows = rows
+        .into_iter()
+        .map(|cells| Mtr {
+            cells: cells.into_iter().map(mtd).collect(),
+            ..Default::default()
+        })
+        .collect();
+    Element::Mtable(Mtable {
+        rows,
+        ..Default::default()
+    })
+}
+pub fn mtd(content: Element) -> Mtd {
+    Mtd {
+        content: content.into(),
+        rowspan: 1,
+        columnspan: 1,
+        columnalign: None,
+        rowalign: None,
+    }
+}
 pub fn mfrac(numerator: Element, denominator: Element) -> Element {
     let numerator = numerator.into();
     let denominator = denominator.into();
     Element::Mfrac(Mfrac {
         numerator,
         denominator,
+        linethickness: None,
+        numalign: ColumnAlign::Center,
+        denomalign: ColumnAlign::Center,
+        bevelled: false,
     })
 }
 pub fn mtext(text: &str) -> Element {
     let text = text.into();
-    Element::Mtext(Mtext { text })
+    Element::Mtext(Mtext {
+        text,
+        mathvariant: None,
+    })
 }
 pub fn msqrt(term: Element) -> Element {
     let term = term.into();
@@ -0,0 +1,242 @@
+//! Default properties of operators, from the operator dictionary of MathML Core.
+//!
+//! Operators are grouped in categories sharing the same spacing and properties. Only the more
+//! common operators are listed, the others get the default spacing of relations.
+//! See <https://w3c.github.io/mathml-core/#operator-dictionary>
+
+use crate::mml_types::{Form, Length};
+
+/// Spacing and properties of an operator, either from the dictionary or from its attributes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OperatorProperties {
+    pub lspace: Length,
+    pub rspace: Length,
+    pub stretchy: bool,
+    pub symmetric: bool,
+    pub largeop: bool,
+    pub movablelimits: bool,
+    pub fence: bool,
+    pub separator: bool,
+    /// Whether the operator stretches horizontally (e.g. arrows) rather than vertically
+    pub horizontal: bool,
+}
+
+impl Default for OperatorProperties {
+    /// Properties of operators which are not in the dictionary
+    fn default() -> Self {
+        Self {
+            lspace: THICK,
+            rspace: THICK,
+            stretchy: false,
+            symmetric: false,
+            largeop: false,
+            movablelimits: false,
+            fence: false,
+            separator: false,
+            horizontal: false,
+        }
+    }
+}
+
+const ZERO: Length = Length::Em(0.0);
+const THIN: Length = Length::Em(3.0 / 18.0);
+const MEDIUM: Length = Length::Em(4.0 / 18.0);
+const THICK: Length = Length::Em(5.0 / 18.0);
+
+/// Categories of the MathML Core operator dictionary
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Category {
+    /// Stretchy relations, e.g. arrows
+    A,
+    /// Relations
+    B,
+    /// Binary operators
+    C,
+    /// Prefix operators without spacing
+    D,
+    /// Postfix operators without spacing
+    E,
+    /// Opening fences
+    F,
+    /// Closing fences
+    G,
+    /// Integrals
+    H,
+    /// Stretchy postfix operators, e.g. accents
+    I,
+    /// Sums and products
+    J,
+    /// Infix operators without spacing
+    K,
+    /// Prefix operators with a space before them
+    L,
+    /// Separators
+    M,
+}
+
+impl Category {
+    fn properties(self) -> OperatorProperties {
+        let spacing = |lspace, rspace| OperatorProperties {
+            lspace,
+            rspace,
+            ..Default::default()
+        };
+        match self {
+            Category::A => OperatorProperties {
+                stretchy: true,
+                ..spacing(THICK, THICK)
+            },
+            Category::B => spacing(THICK, THICK),
+            Category::C => spacing(MEDIUM, MEDIUM),
+            Category::D | Category::E | Category::K => spacing(ZERO, ZERO),
+            Category::F | Category::G => OperatorProperties {
+                stretchy: true,
+                symmetric: true,
+                fence: true,
+                ..spacing(ZERO, ZERO)
+            },
+            Category::H => OperatorProperties {
+                symmetric: true,
+                largeop: true,
+                ..spacing(THIN, THIN)
+            },
+            Category::I => OperatorProperties {
+                stretchy: true,
+                ..spacing(ZERO, ZERO)
+            },
+            Category::J => OperatorProperties {
+                symmetric: true,
+                largeop: true,
+                movablelimits: true,
+                ..spacing(THIN, THIN)
+            },
+            Category::L => spacing(THIN, ZERO),
+            Category::M => OperatorProperties {
+                separator: true,
+                ..spacing(ZERO, THIN)
+            },
+        }
+    }
+}
+
+/// Properties of an operator in a given form.
+///
+/// If the dictionary has no entry for that form, the entry of another form is used, trying infix,
+/// then postfix and prefix.
+pub fn operator_properties(operator: &str, form: Form) -> OperatorProperties {
+    let mut properties = [form, Form::Infix, Form::Postfix, Form::Prefix]
+        .into_iter()
+        .find_map(|form| category(operator, form))
+        .map(Category::properties)
+        .unwrap_or_default();
+    properties.horizontal = HORIZONTAL.contains(&operator);
+    properties
+}
+
+fn category(operator: &str, form: Form) -> Option<Category> {
+    use Category::*;
+    let category = match (form, operator) {
+        (
+            Form::Infix,
+            "←" | "↑" | "→" | "↓" | "↔" | "↕" | "↖" | "↗" | "↘" | "↙" | "↚" | "↛" | "↞" | "↠" | "↢"
+            | "↣" | "↤" | "↥" | "↦" | "↧" | "↩" | "↪" | "↼" | "↽" | "↾" | "↿" | "⇀" | "⇁" | "⇂"
+            | "⇃" | "⇄" | "⇆" | "⇇" | "⇈" | "⇉" | "⇊" | "⇋" | "⇌" | "⇐" | "⇑" | "⇒" | "⇓" | "⇔"
+            | "⇕" | "⟵" | "⟶" | "⟷" | "⟸" | "⟹" | "⟺" | "⟻" | "⟼" | "⟽" | "⟾",
+        ) => A,
+        (
+            Form::Infix,
+            "=" | "<" | ">" | "≠" | "≤" | "≥" | "≦" | "≧" | "≨" | "≩" | "≪" | "≫" | "≮" | "≯" | "≰"
+            | "≱" | "≲" | "≳" | "≺" | "≻" | "≼" | "≽" | "≡" | "≢" | "≈" | "≉" | "≃" | "≄" | "≅"
+            | "≆" | "≇" | "≍" | "≐" | "≔" | "≕" | "≜" | "≝" | "≟" | "∼" | "≁" | "∝" | "∈" | "∉"
+            | "∋" | "∌" | "⊂" | "⊃" | "⊄" | "⊅" | "⊆" | "⊇" | "⊈" | "⊉" | "⊊" | "⊋" | "⊏" | "⊐"
+            | "⊑" | "⊒" | "⊢" | "⊣" | "⊤" | "⊥" | "⊨" | "⊩" | "⊪" | "⊫" | "⊬" | "⊭" | "∣" | "∤"
+            | "∥" | "∦" | "⊲" | "⊳" | "⊴" | "⊵" | "⋈" | "⩽" | "⩾" | ":=" | "==" | "!=" | "<="
+            | ">=" | "->" | "<-" | "=>" | "::" | "∴" | "∵" | "∷" | "∶",
+        ) => B,
+        (
+            Form::Infix,
+            "+" | "-" | "−" | "±" | "∓" | "∔" | "×" | "÷" | "*" | "∗" | "⋅" | "·" | "∘" | "∙" | "⋆"
+            | "∪" | "∩" | "⊎" | "⊓" | "⊔" | "∧" | "∨" | "⊕" | "⊖" | "⊗" | "⊘" | "⊙" | "⊚" | "⊛"
+            | "⊞" | "⊟" | "⊠" | "⊡" | "⋄" | "⋉" | "⋊" | "⋋" | "⋌" | "⋎" | "⋏" | "≀" | "∖" | "⨯"
+            | "&&" | "||" | "&" | "%" | "^",
+        ) => C,
+        (
+            Form::Prefix,
+            "+" | "-" | "−" | "±" | "∓" | "¬" | "!" | "~" | "∂" | "∇" | "∆" | "′" | "++" | "--"
+            | "∀" | "∃" | "∄",
+        ) => D,
+        (
+            Form::Postfix,
+            "!" | "!!" | "%" | "'" | "′" | "″" | "‴" | "⁗" | "°" | "++" | "--" | "♭" | "♮" | "♯"
+            | "…" | "⋯",
+        ) => E,
+        (Form::Prefix, "(" | "[" | "{" | "|" | "‖" | "⌈" | "⌊" | "⟨" | "⟦" | "⟪" | "⦃" | "〈") => {
+            F
+        }
+        (Form::Postfix, ")" | "]" | "}" | "|" | "‖" | "⌉" | "⌋" | "⟩" | "⟧" | "⟫" | "⦄" | "〉") => {
+            G
+        }
+        (Form::Prefix, "∫" | "∬" | "∭" | "∮" | "∯" | "∰" | "∱" | "∲" | "∳" | "⨌" | "⨍" | "⨏") => {
+            H
+        }
+        (
+            Form::Postfix,
+            "¯" | "‾" | "^" | "ˆ" | "ˇ" | "˜" | "~" | "_" | "⏜" | "⏝" | "⏞" | "⏟" | "⏠" | "⏡" | "⎴"
+            | "⎵",
+        ) => I,
+        (
+            Form::Prefix,
+            "∑" | "∏" | "∐" | "⋀" | "⋁" | "⋂" | "⋃" | "⨀" | "⨁" | "⨂" | "⨄" | "⨆" | "lim" | "max"
+            | "min" | "sup" | "inf" | "limsup" | "liminf",
+        ) => J,
+        // Slashes, and the invisible function application, times, separator and plus
+        (Form::Infix, "\u{2061}" | "\u{2062}" | "\u{2063}" | "\u{2064}" | "/" | "\\" | "_") => K,
+        (Form::Prefix, "√" | "∛" | "∜" | "ⅆ") => L,
+        (Form::Infix, "," | ";") => M,
+        _ => return None,
+    };
+    Some(category)
+}
+
+/// Operators which stretch horizontally, e.g. over or under an expression, rather than vertically
+const HORIZONTAL: &[&str] = &[
+    "←", "→", "↔", "↚", "↛", "↞", "↠", "↢", "↣", "↤", "↦", "↩", "↪", "↼", "↽", "⇀", "⇁", "⇄", "⇆",
+    "⇇", "⇉", "⇋", "⇌", "⇐", "⇒", "⇔", "⟵", "⟶", "⟷", "⟸", "⟹", "⟺", "⟻", "⟼", "⟽", "⟾", "¯", "‾",
+    "_", "^", "ˆ", "ˇ", "˜", "~", "⏜", "⏝", "⏞", "⏟", "⏠", "⏡", "⎴", "⎵",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forms() {
+        let minus = operator_properties("−", Form::Infix);
+        assert_eq!((minus.lspace, minus.rspace), (MEDIUM, MEDIUM));
+        let minus = operator_properties("−", Form::Prefix);
+        assert_eq!((minus.lspace, minus.rspace), (ZERO, ZERO));
+
+        // Fences only have prefix and postfix entries
+        let parenthesis = operator_properties("(", Form::Infix);
+        assert!(parenthesis.stretchy && parenthesis.fence);
+
+        let comma = operator_properties(",", Form::Postfix);
+        assert_eq!((comma.lspace, comma.rspace), (ZERO, THIN));
+        assert!(comma.separator);
+    }
+
+    #[test]
+    fn unknown_operator() {
+        assert_eq!(
+            operator_properties("⌘", Form::Infix),
+            OperatorProperties::default()
+        );
+    }
+
+    #[test]
+    fn stretch_direction() {
+        assert!(operator_properties("→", Form::Infix).horizontal);
+        assert!(!operator_properties("↑", Form::Infix).horizontal);
+        assert!(!operator_properties("(", Form::Prefix).horizontal);
+    }
+}
@@ -0,0 +1,1069 @@
+//! Parsing of presentation MathML documents into [`Math`] trees.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use roxmltree::{Document, Node};
+
+use crate::canvas::Color;
+use crate::mml_types::{
+    ColumnAlign, Element, Form, Length, LineStyle, LineThickness, Math, MathVariant, Menclose,
+    Mfrac, Mi, Mmultiscripts, Mn, Mo, Mover, Mpadded, Mspace, Mstyle, Mtable, Mtd, Mtext, Mtr,
+    Munder, Munderover, Notation, PaddedLength, PaddedValue, PseudoUnit, RowAlign, ScriptLevel,
+    ScriptPair, mphantom, mroot, mrow, msqrt, msub, msubsup, msup, mtd,
+};
+
+#[derive(Debug)]
+pub enum ParseError {
+    /// The input is not well-formed XML
+    Xml(roxmltree::Error),
+    /// The root element of the document is not `<math>`
+    NotMath {
+        name: String,
+        line: u32,
+        column: u32,
+    },
+    /// An element which is not a supported presentation element
+    UnknownElement {
+        name: String,
+        line: u32,
+        column: u32,
+    },
+    /// An element with a fixed number of children (e.g. `<mfrac>`) has the wrong amount
+    WrongChildCount {
+        name: String,
+        expected: usize,
+        found: usize,
+        line: u32,
+        column: u32,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Xml(error) => write!(f, "invalid XML: {error}"),
+            ParseError::NotMath { name, line, column } => write!(
+                f,
+                "expected <math> root element but found <{name}> at {line}:{column}"
+            ),
+            ParseError::UnknownElement { name, line, column } => {
+                write!(f, "unknown element <{name}> at {line}:{column}")
+            }
+            ParseError::WrongChildCount {
+                name,
+                expected,
+                found,
+                line,
+                column,
+            } => write!(
+                f,
+                "<{name}> at {line}:{column} expects {expected} children but has {found}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Xml(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<roxmltree::Error> for ParseError {
+    fn from(error: roxmltree::Error) -> Self {
+        ParseError::Xml(error)
+    }
+}
+
+/// Parse a MathML document whose root is a `<math>` element.
+///
+/// Only presentation elements are supported, anything else is reported as
+/// [`ParseError::UnknownElement`] along with its position in the input.
+pub fn parse_mathml(text: &str) -> Result<Math, ParseError> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "math" {
+        let (line, column) = position(&root);
+        return Err(ParseError::NotMath {
+            name: root.tag_name().name().into(),
+            line,
+            column,
+        });
+    }
+
+    let attributes = root
+        .attributes()
+        .map(|attribute| (attribute.name().into(), attribute.value().into()))
+        .collect::<HashMap<String, String>>();
+    let content = parse_inferred_mrow(&root)?;
+
+    Ok(Math {
+        attributes,
+        content,
+    })
+}
+
+fn parse_element(node: &Node) -> Result<Element, ParseError> {
+    let name = node.tag_name().name();
+    let element = match name {
+        "mi" => Element::Mi(Mi {
+            identifier: token_text(node),
+            mathvariant: node.attribute("mathvariant").and_then(parse_math_variant),
+        }),
+        "mn" => Element::Mn(Mn {
+            number: token_text(node),
+            mathvariant: node.attribute("mathvariant").and_then(parse_math_variant),
+        }),
+        "mo" => Element::Mo(Mo {
+            operator: token_text(node),
+            form: match node.attribute("form").map(str::trim) {
+                Some("prefix") => Some(Form::Prefix),
+                Some("infix") => Some(Form::Infix),
+                Some("postfix") => Some(Form::Postfix),
+                _ => None,
+            },
+            lspace: length_attribute(node, "lspace"),
+            rspace: length_attribute(node, "rspace"),
+            stretchy: boolean_attribute(node, "stretchy"),
+            symmetric: boolean_attribute(node, "symmetric"),
+            largeop: boolean_attribute(node, "largeop"),
+            movablelimits: boolean_attribute(node, "movablelimits"),
+            fence: boolean_attribute(node, "fence"),
+            separator: boolean_attribute(node, "separator"),
+            mathvariant: node.attribute("mathvariant").and_then(parse_math_variant),
+        }),
+        "mtext" => Element::Mtext(Mtext {
+            text: token_text(node),
+            mathvariant: node.attribute("mathvariant").and_then(parse_math_variant),
+        }),
+        "mrow" => mrow(parse_children(node)?),
+        "mphantom" => mphantom(parse_children(node)?),
+        "mspace" => Element::Mspace(Mspace {
+            width: length_attribute(node, "width"),
+            height: length_attribute(node, "height"),
+            depth: length_attribute(node, "depth"),
+        }),
+        "mpadded" => {
+            let attribute = |name| node.attribute(name).and_then(parse_padded_length);
+            Element::Mpadded(Mpadded {
+                content: parse_inferred_mrow(node)?.into(),
+                width: attribute("width"),
+                height: attribute("height"),
+                depth: attribute("depth"),
+                lspace: attribute("lspace"),
+                voffset: attribute("voffset"),
+            })
+        }
+        "msqrt" => msqrt(parse_inferred_mrow(node)?),
+        "mfrac" => {
+            let [numerator, denominator] = parse_fixed_children(node)?;
+            Element::Mfrac(Mfrac {
+                numerator: numerator.into(),
+                denominator: denominator.into(),
+                linethickness: node
+                    .attribute("linethickness")
+                    .and_then(parse_line_thickness),
+                numalign: node
+                    .attribute("numalign")
+                    .and_then(parse_column_align)
+                    .unwrap_or_default(),
+                denomalign: node
+                    .attribute("denomalign")
+                    .and_then(parse_column_align)
+                    .unwrap_or_default(),
+                bevelled: boolean_attribute(node, "bevelled").unwrap_or(false),
+            })
+        }
+        "msub" => {
+            let [base, subscript] = parse_fixed_children(node)?;
+            msub(base, subscript)
+        }
+        "msup" => {
+            let [base, superscript] = parse_fixed_children(node)?;
+            msup(base, superscript)
+        }
+        "msubsup" => {
+            let [base, subscript, superscript] = parse_fixed_children(node)?;
+            msubsup(base, subscript, superscript)
+        }
+        "munder" => {
+            let [base, underscript] = parse_fixed_children(node)?;
+            Element::Munder(Munder {
+                base: base.into(),
+                underscript: underscript.into(),
+                accentunder: boolean_attribute(node, "accentunder"),
+            })
+        }
+        "mover" => {
+            let [base, overscript] = parse_fixed_children(node)?;
+            Element::Mover(Mover {
+                base: base.into(),
+                overscript: overscript.into(),
+                accent: boolean_attribute(node, "accent"),
+            })
+        }
+        "munderover" => {
+            let [base, underscript, overscript] = parse_fixed_children(node)?;
+            Element::Munderover(Munderover {
+                base: base.into(),
+                underscript: underscript.into(),
+                overscript: overscript.into(),
+                accentunder: boolean_attribute(node, "accentunder"),
+                accent: boolean_attribute(node, "accent"),
+            })
+        }
+        "mroot" => {
+            let [base, index] = parse_fixed_children(node)?;
+            mroot(base, Some(index))
+        }
+        "mmultiscripts" => parse_mmultiscripts(node)?,
+        "mstyle" => Element::Mstyle(Mstyle {
+            content: parse_inferred_mrow(node)?.into(),
+            displaystyle: boolean_attribute(node, "displaystyle"),
+            scriptlevel: node.attribute("scriptlevel").and_then(parse_script_level),
+            scriptminsize: length_attribute(node, "scriptminsize"),
+            mathsize: length_attribute(node, "mathsize"),
+            mathcolor: node.attribute("mathcolor").and_then(parse_color),
+            mathbackground: node.attribute("mathbackground").and_then(parse_color),
+            mathvariant: node.attribute("mathvariant").and_then(parse_math_variant),
+        }),
+        "menclose" => Element::Menclose(Menclose {
+            // Unknown notations are ignored, so that the others are still drawn
+            notation: match node.attribute("notation") {
+                Some(notation) => notation
+                    .split_whitespace()
+                    .filter_map(parse_notation)
+                    .collect(),
+                None => vec![Notation::Longdiv],
+            },
+            content: parse_inferred_mrow(node)?.into(),
+        }),
+        "mtable" => Element::Mtable(Mtable {
+            rows: element_children(node)
+                .map(|child| parse_mtr(&child))
+                .collect::<Result<_, _>>()?,
+            columnalign: list_attribute(node, "columnalign", parse_column_align),
+            rowalign: list_attribute(node, "rowalign", parse_row_align),
+            columnspacing: list_attribute(node, "columnspacing", parse_length),
+            rowspacing: list_attribute(node, "rowspacing", parse_length),
+            columnlines: list_attribute(node, "columnlines", parse_line_style),
+            rowlines: list_attribute(node, "rowlines", parse_line_style),
+            frame: node
+                .attribute("frame")
+                .and_then(parse_line_style)
+                .unwrap_or_default(),
+        }),
+        // Rows and cells outside of a table are kept as rows
+        "mtr" | "mtd" => mrow(parse_children(node)?),
+        // Only the presentation markup (first child) of semantics is rendered, annotations are dropped
+        "semantics" => match element_children(node).next() {
+            Some(child) => parse_element(&child)?,
+            None => mrow(vec![]),
+        },
+        _ => {
+            let (line, column) = position(node);
+            return Err(ParseError::UnknownElement {
+                name: name.into(),
+                line,
+                column,
+            });
+        }
+    };
+    Ok(element)
+}
+
+/// Row of a table, any other element is a row with a single cell
+fn parse_mtr(node: &Node) -> Result<Mtr, ParseError> {
+    if node.tag_name().name() != "mtr" {
+        return Ok(Mtr {
+            cells: vec![parse_mtd(node)?],
+            ..Default::default()
+        });
+    }
+    Ok(Mtr {
+        cells: element_children(node)
+            .map(|child| parse_mtd(&child))
+            .collect::<Result<_, _>>()?,
+        columnalign: list_attribute(node, "columnalign", parse_column_align),
+        rowalign: node.attribute("rowalign").and_then(parse_row_align),
+    })
+}
+
+/// Cell of a table, any other element is the content of a cell
+fn parse_mtd(node: &Node) -> Result<Mtd, ParseError> {
+    if node.tag_name().name() != "mtd" {
+        return Ok(mtd(parse_element(node)?));
+    }
+    let span = |name| {
+        node.attribute(name)
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(1)
+            .max(1)
+    };
+    Ok(Mtd {
+        content: parse_inferred_mrow(node)?.into(),
+        rowspan: span("rowspan"),
+        columnspan: span("columnspan"),
+        columnalign: node.attribute("columnalign").and_then(parse_column_align),
+        rowalign: node.attribute("rowalign").and_then(parse_row_align),
+    })
+}
+
+fn element_children<'a, 'input>(node: &Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(Node::is_element)
+}
+
+fn parse_children(node: &Node) -> Result<Vec<Element>, ParseError> {
+    element_children(node)
+        .map(|child| parse_element(&child))
+        .collect()
+}
+
+/// Elements such as `<math>` and `<msqrt>` accept any number of children which are treated as an
+/// `<mrow>` unless there is exactly one of them.
+fn parse_inferred_mrow(node: &Node) -> Result<Element, ParseError> {
+    let mut children = parse_children(node)?;
+    if children.len() == 1 {
+        Ok(children.remove(0))
+    } else {
+        Ok(mrow(children))
+    }
+}
+
+fn parse_fixed_children<const N: usize>(node: &Node) -> Result<[Element; N], ParseError> {
+    let children = parse_children(node)?;
+    let found = children.len();
+    children
+        .try_into()
+        .map_err(|_| wrong_child_count(node, N, found))
+}
+
+fn wrong_child_count(node: &Node, expected: usize, found: usize) -> ParseError {
+    let (line, column) = position(node);
+    ParseError::WrongChildCount {
+        name: node.tag_name().name().into(),
+        expected,
+        found,
+        line,
+        column,
+    }
+}
+
+/// Base followed by pairs of subscript and superscript, those after `<mprescripts/>` being before
+/// the base. Missing scripts are marked with `<none/>`.
+fn parse_mmultiscripts(node: &Node) -> Result<Element, ParseError> {
+    let children = element_children(node).collect::<Vec<_>>();
+    let Some((base, scripts)) = children.split_first() else {
+        return Err(wrong_child_count(node, 1, 0));
+    };
+    let (postscripts, prescripts) = match scripts
+        .iter()
+        .position(|child| child.tag_name().name() == "mprescripts")
+    {
+        Some(index) => (&scripts[..index], &scripts[index + 1..]),
+        None => (scripts, &[][..]),
+    };
+    let parse_pairs = |scripts: &[Node]| {
+        if scripts.len() % 2 != 0 {
+            // One more script is needed to complete the last pair
+            return Err(wrong_child_count(node, children.len() + 1, children.len()));
+        }
+        let parse_script = |script: &Node| match script.tag_name().name() {
+            "none" => Ok(None),
+            _ => parse_element(script).map(Some),
+        };
+        scripts
+            .chunks(2)
+            .map(|pair| {
+                Ok(ScriptPair {
+                    subscript: parse_script(&pair[0])?,
+                    superscript: parse_script(&pair[1])?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok(Element::Mmultiscripts(Mmultiscripts {
+        base: parse_element(base)?.into(),
+        postscripts: parse_pairs(postscripts)?,
+        prescripts: parse_pairs(prescripts)?,
+    }))
+}
+
+/// Text content of a token element with surrounding whitespace removed and inner whitespace
+/// collapsed, as specified by MathML.
+fn token_text(node: &Node) -> String {
+    node.descendants()
+        .filter(Node::is_text)
+        .filter_map(|text| text.text())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Value of a boolean attribute, `None` if it is absent or invalid
+fn boolean_attribute(node: &Node, name: &str) -> Option<bool> {
+    match node.attribute(name)?.trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Value of a length attribute (see [`parse_length`]), `None` if it is absent or invalid
+fn length_attribute(node: &Node, name: &str) -> Option<Length> {
+    parse_length(node.attribute(name)?)
+}
+
+/// Whitespace separated values of an attribute, empty if it is absent or any value is invalid
+fn list_attribute<T>(node: &Node, name: &str, parse: impl Fn(&str) -> Option<T>) -> Vec<T> {
+    node.attribute(name)
+        .and_then(|value| value.split_whitespace().map(parse).collect())
+        .unwrap_or_default()
+}
+
+/// Number of pixels in an inch, as in CSS
+const PX_PER_INCH: f32 = 96.0;
+
+/// Length with a unit (em, ex, px, pt, pc, in, cm, mm or %), or a named space such as
+/// `thinmathspace` or `negativethinmathspace`
+fn parse_length(value: &str) -> Option<Length> {
+    let value = value.trim();
+    if let Some(length) = parse_named_space(value) {
+        return Some(length);
+    }
+    let unit_start = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '+' | '-')))
+        .unwrap_or(value.len());
+    let number: f32 = value[..unit_start].parse().ok()?;
+    match value[unit_start..].trim() {
+        "em" => Some(Length::Em(number)),
+        "ex" => Some(Length::Ex(number)),
+        "px" => Some(Length::Px(number)),
+        "pt" => Some(Length::Px(number * PX_PER_INCH / 72.0)),
+        "pc" => Some(Length::Px(number * PX_PER_INCH / 6.0)),
+        "in" => Some(Length::Px(number * PX_PER_INCH)),
+        "cm" => Some(Length::Px(number * PX_PER_INCH / 2.54)),
+        "mm" => Some(Length::Px(number * PX_PER_INCH / 25.4)),
+        "%" => Some(Length::Percent(number)),
+        // Unitless zero is the only length allowed without a unit
+        "" if number == 0.0 => Some(Length::Px(0.0)),
+        _ => None,
+    }
+}
+
+/// Named spaces are multiples of 1/18 em, and can be negated with a `negative` prefix
+fn parse_named_space(value: &str) -> Option<Length> {
+    let (sign, name) = match value.strip_prefix("negative") {
+        Some(name) => (-1.0, name),
+        None => (1.0, value),
+    };
+    let eighteenths = match name {
+        "veryverythinmathspace" => 1.0,
+        "verythinmathspace" => 2.0,
+        "thinmathspace" => 3.0,
+        "mediummathspace" => 4.0,
+        "thickmathspace" => 5.0,
+        "verythickmathspace" => 6.0,
+        "veryverythickmathspace" => 7.0,
+        _ => return None,
+    };
+    Some(Length::Em(sign * eighteenths / 18.0))
+}
+
+/// A length, or a multiple of a dimension of the content (`2width`, `50% height`) or of a named
+/// space, with a leading sign to change the dimension rather than set it
+fn parse_padded_length(value: &str) -> Option<PaddedLength> {
+    let value = value.trim();
+    let relative = value.starts_with(['+', '-']);
+    let pseudo_units = [
+        ("width", PseudoUnit::Width),
+        ("height", PseudoUnit::Height),
+        ("depth", PseudoUnit::Depth),
+    ];
+    for (name, unit) in pseudo_units {
+        if let Some(number) = value.strip_suffix(name) {
+            let number = number.trim();
+            let ratio = match number.strip_suffix('%') {
+                Some(percentage) => percentage.trim().parse::<f32>().ok()? / 100.0,
+                None => number.parse().ok()?,
+            };
+            return Some(PaddedLength {
+                value: PaddedValue::Dimension(ratio, unit),
+                relative,
+            });
+        }
+    }
+    let length = parse_length(value).or_else(|| {
+        let name_start = value.find(|c: char| c.is_ascii_alphabetic())?;
+        let ratio: f32 = value[..name_start].trim().parse().ok()?;
+        match parse_named_space(&value[name_start..])? {
+            Length::Em(em) => Some(Length::Em(ratio * em)),
+            _ => None,
+        }
+    })?;
+    Some(PaddedLength {
+        value: PaddedValue::Length(length),
+        relative,
+    })
+}
+
+/// `thin`, `medium` and `thick` are relative to the default thickness, as are percentages and
+/// (deprecated) unitless numbers
+fn parse_line_thickness(value: &str) -> Option<LineThickness> {
+    let value = value.trim();
+    let relative = match value {
+        "thin" => Some(0.5),
+        "medium" => Some(1.0),
+        "thick" => Some(2.0),
+        _ => match value.strip_suffix('%') {
+            Some(percentage) => percentage.trim().parse().ok().map(|p: f32| p / 100.0),
+            None => value.parse().ok(),
+        },
+    };
+    match relative {
+        Some(relative) => Some(LineThickness::Relative(relative)),
+        None => parse_length(value).map(LineThickness::Length),
+    }
+}
+
+fn parse_column_align(value: &str) -> Option<ColumnAlign> {
+    match value.trim() {
+        "left" => Some(ColumnAlign::Left),
+        "center" => Some(ColumnAlign::Center),
+        "right" => Some(ColumnAlign::Right),
+        _ => None,
+    }
+}
+
+fn parse_row_align(value: &str) -> Option<RowAlign> {
+    match value.trim() {
+        "top" => Some(RowAlign::Top),
+        "bottom" => Some(RowAlign::Bottom),
+        "center" => Some(RowAlign::Center),
+        "baseline" => Some(RowAlign::Baseline),
+        "axis" => Some(RowAlign::Axis),
+        _ => None,
+    }
+}
+
+fn parse_notation(value: &str) -> Option<Notation> {
+    match value {
+        "box" => Some(Notation::Box),
+        "roundedbox" => Some(Notation::RoundedBox),
+        "circle" => Some(Notation::Circle),
+        "left" => Some(Notation::Left),
+        "right" => Some(Notation::Right),
+        "top" => Some(Notation::Top),
+        "bottom" => Some(Notation::Bottom),
+        "updiagonalstrike" => Some(Notation::UpDiagonalStrike),
+        "downdiagonalstrike" => Some(Notation::DownDiagonalStrike),
+        "horizontalstrike" => Some(Notation::HorizontalStrike),
+        "verticalstrike" => Some(Notation::VerticalStrike),
+        "actuarial" => Some(Notation::Actuarial),
+        "madruwb" => Some(Notation::Madruwb),
+        "longdiv" => Some(Notation::Longdiv),
+        "radical" => Some(Notation::Radical),
+        _ => None,
+    }
+}
+
+fn parse_line_style(value: &str) -> Option<LineStyle> {
+    match value.trim() {
+        "none" => Some(LineStyle::None),
+        "solid" => Some(LineStyle::Solid),
+        "dashed" => Some(LineStyle::Dashed),
+        _ => None,
+    }
+}
+
+/// `+n` and `-n` are relative to the inherited level, `n` sets it
+fn parse_script_level(value: &str) -> Option<ScriptLevel> {
+    let value = value.trim();
+    if value.starts_with(['+', '-']) {
+        value.parse().ok().map(ScriptLevel::Relative)
+    } else {
+        value.parse().ok().map(ScriptLevel::Absolute)
+    }
+}
+
+fn parse_math_variant(value: &str) -> Option<MathVariant> {
+    match value.trim() {
+        "normal" => Some(MathVariant::Normal),
+        "bold" => Some(MathVariant::Bold),
+        "italic" => Some(MathVariant::Italic),
+        "bold-italic" => Some(MathVariant::BoldItalic),
+        "double-struck" => Some(MathVariant::DoubleStruck),
+        "bold-fraktur" => Some(MathVariant::BoldFraktur),
+        "script" => Some(MathVariant::Script),
+        "bold-script" => Some(MathVariant::BoldScript),
+        "fraktur" => Some(MathVariant::Fraktur),
+        "sans-serif" => Some(MathVariant::SansSerif),
+        "bold-sans-serif" => Some(MathVariant::BoldSansSerif),
+        "sans-serif-italic" => Some(MathVariant::SansSerifItalic),
+        "sans-serif-bold-italic" => Some(MathVariant::SansSerifBoldItalic),
+        "monospace" => Some(MathVariant::Monospace),
+        "initial" => Some(MathVariant::Initial),
+        "tailed" => Some(MathVariant::Tailed),
+        "looped" => Some(MathVariant::Looped),
+        "stretched" => Some(MathVariant::Stretched),
+        _ => None,
+    }
+}
+
+/// A color as `#rgb`, `#rrggbb`, `#rrggbbaa` or one of the basic CSS color names
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    let Some(hex) = value.strip_prefix('#') else {
+        let (r, g, b) = match value.to_ascii_lowercase().as_str() {
+            "transparent" => return Some(Color::from_rgba8(0, 0, 0, 0)),
+            "black" => (0, 0, 0),
+            "silver" => (192, 192, 192),
+            "gray" | "grey" => (128, 128, 128),
+            "white" => (255, 255, 255),
+            "maroon" => (128, 0, 0),
+            "red" => (255, 0, 0),
+            "purple" => (128, 0, 128),
+            "fuchsia" | "magenta" => (255, 0, 255),
+            "green" => (0, 128, 0),
+            "lime" => (0, 255, 0),
+            "olive" => (128, 128, 0),
+            "yellow" => (255, 255, 0),
+            "navy" => (0, 0, 128),
+            "blue" => (0, 0, 255),
+            "teal" => (0, 128, 128),
+            "aqua" | "cyan" => (0, 255, 255),
+            "orange" => (255, 165, 0),
+            _ => return None,
+        };
+        return Some(Color::from_rgba8(r, g, b, 255));
+    };
+    if !hex.is_ascii() {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 => Some(Color::from_rgba8(
+            digit(0)? * 17,
+            digit(1)? * 17,
+            digit(2)? * 17,
+            255,
+        )),
+        6 => Some(Color::from_rgba8(byte(0)?, byte(2)?, byte(4)?, 255)),
+        8 => Some(Color::from_rgba8(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+        _ => None,
+    }
+}
+
+fn position(node: &Node) -> (u32, u32) {
+    let position = node.document().text_pos_at(node.range().start);
+    (position.row, position.col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mml_types::{Mo, Mrow};
+
+    #[test]
+    fn single_child_is_not_wrapped() {
+        let math = parse_mathml("<math display=\"block\"><mi> x </mi></math>").unwrap();
+        assert_eq!(math.attributes["display"], "block");
+        assert!(matches!(math.content, Element::Mi(Mi { identifier, .. }) if identifier == "x"));
+    }
+
+    #[test]
+    fn implicit_mrow() {
+        let math = parse_mathml(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML">
+                <mi>x</mi>
+                <mo>+</mo>
+                <mfrac><mn>1</mn><mn>2</mn></mfrac>
+            </math>"#,
+        )
+        .unwrap();
+        let Element::Mrow(Mrow { terms }) = math.content else {
+            panic!("expected an implicit mrow");
+        };
+        assert_eq!(terms.len(), 3);
+        assert!(matches!(&terms[1], Element::Mo(Mo { operator, .. }) if operator == "+"));
+        assert!(matches!(&terms[2], Element::Mfrac(Mfrac { .. })));
+    }
+
+    #[test]
+    fn semantics_annotation_is_dropped() {
+        let math = parse_mathml(
+            r#"<math><semantics><mrow><mi>a</mi><mi>b</mi></mrow>
+            <annotation encoding="application/x-tex">ab</annotation></semantics></math>"#,
+        )
+        .unwrap();
+        assert!(matches!(math.content, Element::Mrow(Mrow { terms }) if terms.len() == 2));
+    }
+
+    #[test]
+    fn unknown_element_position() {
+        let error = parse_mathml("<math>\n  <mi>x</mi>\n  <foo/>\n</math>").err();
+        assert!(matches!(
+            error,
+            Some(ParseError::UnknownElement { name, line: 3, column: 3 }) if name == "foo"
+        ));
+    }
+
+    #[test]
+    fn wrong_child_count() {
+        let error = parse_mathml("<math><mfrac><mn>1</mn></mfrac></math>").err();
+        assert!(matches!(
+            error,
+            Some(ParseError::WrongChildCount {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn stretchy_attribute() {
+        let math = parse_mathml(r#"<math><mo stretchy="true">(</mo><mo>)</mo></math>"#).unwrap();
+        let Element::Mrow(Mrow { terms }) = math.content else {
+            panic!("expected an implicit mrow");
+        };
+        assert!(matches!(
+            &terms[0],
+            Element::Mo(Mo {
+                stretchy: Some(true),
+                ..
+            })
+        ));
+        assert!(matches!(&terms[1], Element::Mo(Mo { stretchy: None, .. })));
+    }
+
+    #[test]
+    fn operator_attributes() {
+        let math = parse_mathml(
+            r#"<math><mo form="prefix" lspace="thinmathspace" rspace="2px" largeop="false">∑</mo></math>"#,
+        )
+        .unwrap();
+        let Element::Mo(mo) = math.content else {
+            panic!("expected an operator");
+        };
+        assert_eq!(mo.form, Some(Form::Prefix));
+        assert_eq!(mo.lspace, Some(Length::Em(3.0 / 18.0)));
+        assert_eq!(mo.rspace, Some(Length::Px(2.0)));
+        assert_eq!(mo.largeop, Some(false));
+        assert_eq!(mo.movablelimits, None);
+    }
+
+    #[test]
+    fn subsup() {
+        let math =
+            parse_mathml("<math><msubsup><mi>x</mi><mn>1</mn><mn>2</mn></msubsup></math>").unwrap();
+        let Element::Msubsup(msubsup) = math.content else {
+            panic!("expected an msubsup");
+        };
+        assert!(matches!(*msubsup.subscript, Element::Mn(Mn { ref number, .. }) if number == "1"));
+        assert!(
+            matches!(*msubsup.superscript, Element::Mn(Mn { ref number, .. }) if number == "2")
+        );
+
+        let error = parse_mathml("<math><msubsup><mi>x</mi><mn>1</mn></msubsup></math>").err();
+        assert!(matches!(
+            error,
+            Some(ParseError::WrongChildCount {
+                expected: 3,
+                found: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn underover_accents() {
+        let math = parse_mathml(
+            r#"<math><munderover accent="true"><mo>∑</mo><mi>i</mi><mi>n</mi></munderover></math>"#,
+        )
+        .unwrap();
+        let Element::Munderover(munderover) = math.content else {
+            panic!("expected an munderover");
+        };
+        assert_eq!(munderover.accent, Some(true));
+        assert_eq!(munderover.accentunder, None);
+        assert!(
+            matches!(*munderover.overscript, Element::Mi(Mi { ref identifier, .. }) if identifier == "n")
+        );
+
+        let math = parse_mathml(r#"<math><mover accent="yes"><mi>x</mi><mo>^</mo></mover></math>"#)
+            .unwrap();
+        assert!(matches!(
+            math.content,
+            Element::Mover(Mover { accent: None, .. })
+        ));
+    }
+
+    #[test]
+    fn table() {
+        let math = parse_mathml(
+            r#"<math><mtable columnalign="left right" rowlines="solid" frame="dashed" columnspacing="1em 2px">
+                <mtr rowalign="top"><mtd columnspan="2"><mi>a</mi><mi>b</mi></mtd></mtr>
+                <mtr><mtd rowspan="0"><mn>1</mn></mtd><mn>2</mn></mtr>
+                <mi>c</mi>
+            </mtable></math>"#,
+        )
+        .unwrap();
+        let Element::Mtable(table) = math.content else {
+            panic!("expected an mtable");
+        };
+        assert_eq!(table.columnalign, [ColumnAlign::Left, ColumnAlign::Right]);
+        assert_eq!(table.columnspacing, [Length::Em(1.0), Length::Px(2.0)]);
+        assert_eq!(table.rowlines, [LineStyle::Solid]);
+        assert_eq!(table.frame, LineStyle::Dashed);
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(table.rows[0].rowalign, Some(RowAlign::Top));
+        assert_eq!(table.rows[0].cells[0].columnspan, 2);
+        assert!(matches!(*table.rows[0].cells[0].content, Element::Mrow(_)));
+        // Invalid spans are ignored and elements outside of cells are wrapped in one
+        assert_eq!(table.rows[1].cells[0].rowspan, 1);
+        assert_eq!(table.rows[1].cells.len(), 2);
+        assert!(matches!(*table.rows[2].cells[0].content, Element::Mi(_)));
+
+        let math = parse_mathml(r#"<math><mtable rowalign="top middle"/></math>"#).unwrap();
+        assert!(
+            matches!(math.content, Element::Mtable(Mtable { rowalign, .. }) if rowalign.is_empty())
+        );
+    }
+
+    #[test]
+    fn multiscripts() {
+        let math = parse_mathml(
+            "<math><mmultiscripts><mi>R</mi><mi>i</mi><none/><none/><mi>j</mi>\
+            <mprescripts/><mn>6</mn><mn>14</mn></mmultiscripts></math>",
+        )
+        .unwrap();
+        let Element::Mmultiscripts(mmultiscripts) = math.content else {
+            panic!("expected an mmultiscripts");
+        };
+        assert_eq!(mmultiscripts.postscripts.len(), 2);
+        assert!(mmultiscripts.postscripts[0].subscript.is_some());
+        assert!(mmultiscripts.postscripts[0].superscript.is_none());
+        assert!(mmultiscripts.postscripts[1].subscript.is_none());
+        assert!(matches!(
+            mmultiscripts.prescripts[0].superscript,
+            Some(Element::Mn(Mn { ref number, .. })) if number == "14"
+        ));
+
+        let error = parse_mathml(
+            "<math><mmultiscripts><mi>R</mi><mi>i</mi><mprescripts/></mmultiscripts></math>",
+        )
+        .err();
+        assert!(matches!(
+            error,
+            Some(ParseError::WrongChildCount {
+                expected: 4,
+                found: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn enclose_notation() {
+        let math = parse_mathml(
+            r#"<math><menclose notation="box  updiagonalstrike phasorangle"><mi>x</mi></menclose></math>"#,
+        )
+        .unwrap();
+        let Element::Menclose(menclose) = math.content else {
+            panic!("expected an menclose");
+        };
+        assert_eq!(
+            menclose.notation,
+            [Notation::Box, Notation::UpDiagonalStrike]
+        );
+
+        let math = parse_mathml("<math><menclose><mn>3</mn><mn>1</mn></menclose></math>").unwrap();
+        let Element::Menclose(menclose) = math.content else {
+            panic!("expected an menclose");
+        };
+        assert_eq!(menclose.notation, [Notation::Longdiv]);
+        assert!(matches!(*menclose.content, Element::Mrow(_)));
+    }
+
+    #[test]
+    fn style_attributes() {
+        let math = parse_mathml(
+            r##"<math><mstyle displaystyle="true" scriptlevel="-1" mathsize="2em" mathcolor="#f00"
+            mathbackground="Teal" mathvariant="double-struck"><mi>R</mi></mstyle></math>"##,
+        )
+        .unwrap();
+        let Element::Mstyle(mstyle) = math.content else {
+            panic!("expected an mstyle");
+        };
+        assert_eq!(mstyle.displaystyle, Some(true));
+        assert_eq!(mstyle.scriptlevel, Some(ScriptLevel::Relative(-1)));
+        assert_eq!(mstyle.mathsize, Some(Length::Em(2.0)));
+        assert_eq!(mstyle.mathcolor, Some(Color::from_rgba8(255, 0, 0, 255)));
+        assert_eq!(
+            mstyle.mathbackground,
+            Some(Color::from_rgba8(0, 128, 128, 255))
+        );
+        assert_eq!(mstyle.mathvariant, Some(MathVariant::DoubleStruck));
+
+        let math = parse_mathml(
+            r##"<math><mstyle scriptlevel="2" scriptminsize="6px" mathcolor="#12345"/></math>"##,
+        )
+        .unwrap();
+        let Element::Mstyle(mstyle) = math.content else {
+            panic!("expected an mstyle");
+        };
+        assert_eq!(mstyle.scriptlevel, Some(ScriptLevel::Absolute(2)));
+        assert_eq!(mstyle.scriptminsize, Some(Length::Px(6.0)));
+        assert_eq!(mstyle.mathcolor, None);
+    }
+
+    #[test]
+    fn token_variants() {
+        let math = parse_mathml(
+            r#"<math><mi mathvariant="bold">x</mi><mn mathvariant="double-struck">1</mn>
+            <mo mathvariant="bold">+</mo><mtext mathvariant="fraktur">ab</mtext><mi>y</mi></math>"#,
+        )
+        .unwrap();
+        let Element::Mrow(Mrow { terms }) = math.content else {
+            panic!("expected an mrow");
+        };
+        assert!(matches!(&terms[0], Element::Mi(Mi { mathvariant, .. })
+            if *mathvariant == Some(MathVariant::Bold)));
+        assert!(matches!(&terms[1], Element::Mn(Mn { mathvariant, .. })
+            if *mathvariant == Some(MathVariant::DoubleStruck)));
+        assert!(matches!(&terms[2], Element::Mo(Mo { mathvariant, .. })
+            if *mathvariant == Some(MathVariant::Bold)));
+        assert!(
+            matches!(&terms[3], Element::Mtext(Mtext { mathvariant, .. })
+            if *mathvariant == Some(MathVariant::Fraktur))
+        );
+        assert!(matches!(
+            &terms[4],
+            Element::Mi(Mi {
+                mathvariant: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn fraction_attributes() {
+        let math = parse_mathml(
+            r#"<math><mfrac linethickness="0" numalign="left" bevelled="true">
+            <mi>n</mi><mi>k</mi></mfrac></math>"#,
+        )
+        .unwrap();
+        let Element::Mfrac(mfrac) = math.content else {
+            panic!("expected an mfrac");
+        };
+        assert_eq!(mfrac.linethickness, Some(LineThickness::Relative(0.0)));
+        assert_eq!(mfrac.numalign, ColumnAlign::Left);
+        assert_eq!(mfrac.denomalign, ColumnAlign::Center);
+        assert!(mfrac.bevelled);
+
+        let thickness = |value: &str| {
+            let math = parse_mathml(&format!(
+                r#"<math><mfrac linethickness="{value}"><mn>1</mn><mn>2</mn></mfrac></math>"#
+            ))
+            .unwrap();
+            let Element::Mfrac(mfrac) = math.content else {
+                panic!("expected an mfrac");
+            };
+            mfrac.linethickness
+        };
+        assert_eq!(thickness("thick"), Some(LineThickness::Relative(2.0)));
+        assert_eq!(thickness("50%"), Some(LineThickness::Relative(0.5)));
+        assert_eq!(
+            thickness("2px"),
+            Some(LineThickness::Length(Length::Px(2.0)))
+        );
+        assert_eq!(thickness("wide"), None);
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(parse_length("1.5ex"), Some(Length::Ex(1.5)));
+        assert_eq!(parse_length("-0.5 em"), Some(Length::Em(-0.5)));
+        assert_eq!(parse_length("72pt"), Some(Length::Px(96.0)));
+        assert_eq!(parse_length("2.54cm"), Some(Length::Px(96.0)));
+        assert_eq!(parse_length("150%"), Some(Length::Percent(150.0)));
+        assert_eq!(
+            parse_length("negativethickmathspace"),
+            Some(Length::Em(-5.0 / 18.0))
+        );
+        assert_eq!(parse_length("0"), Some(Length::Px(0.0)));
+        assert_eq!(parse_length("2"), None);
+        assert_eq!(parse_length("1furlong"), None);
+
+        let math = parse_mathml(r#"<math><mspace width="1em" depth="2mm"/></math>"#).unwrap();
+        let Element::Mspace(mspace) = math.content else {
+            panic!("expected an mspace");
+        };
+        assert_eq!(mspace.width, Some(Length::Em(1.0)));
+        assert_eq!(mspace.height, None);
+        assert!(matches!(mspace.depth, Some(Length::Px(px)) if (px - 7.56).abs() < 0.01));
+    }
+
+    #[test]
+    fn padded_attributes() {
+        let math = parse_mathml(
+            r#"<math><mpadded width="+2thinmathspace" height="50% depth" depth="-1ex"
+            lspace="0.5width" voffset="wide"><mi>x</mi><mi>y</mi></mpadded></math>"#,
+        )
+        .unwrap();
+        let Element::Mpadded(mpadded) = math.content else {
+            panic!("expected an mpadded");
+        };
+        assert_eq!(
+            mpadded.width,
+            Some(PaddedLength {
+                value: PaddedValue::Length(Length::Em(2.0 * 3.0 / 18.0)),
+                relative: true,
+            })
+        );
+        assert_eq!(
+            mpadded.height,
+            Some(PaddedLength {
+                value: PaddedValue::Dimension(0.5, PseudoUnit::Depth),
+                relative: false,
+            })
+        );
+        assert_eq!(
+            mpadded.depth,
+            Some(PaddedLength {
+                value: PaddedValue::Length(Length::Ex(-1.0)),
+                relative: true,
+            })
+        );
+        assert_eq!(
+            mpadded.lspace,
+            Some(PaddedLength {
+                value: PaddedValue::Dimension(0.5, PseudoUnit::Width),
+                relative: false,
+            })
+        );
+        assert_eq!(mpadded.voffset, None);
+        assert!(matches!(*mpadded.content, Element::Mrow(_)));
+
+        assert_eq!(
+            parse_padded_length("+150%"),
+            Some(PaddedLength {
+                value: PaddedValue::Length(Length::Percent(150.0)),
+                relative: true,
+            })
+        );
+    }
+
+    #[test]
+    fn not_math() {
+        let error = parse_mathml("<mrow><mi>x</mi></mrow>").err();
+        assert!(matches!(error, Some(ParseError::NotMath { .. })));
+    }
+}
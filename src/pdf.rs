@@ -0,0 +1,279 @@
+//! PDF output backend, for print quality equations.
+//!
+//! Glyphs are drawn as filled outlines, so the output doesn't embed any font. The document is
+//! written by hand and left uncompressed, it only uses a small subset of the format.
+//! See <https://opensource.adobe.com/dc-acrobat-sdk-docs/pdfstandards/PDF32000_2008.pdf>
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::canvas::{Color, MathCanvas, Path, PathCommand, format_number as number};
+
+/// Canvas which records everything drawn on it as a PDF content stream.
+///
+/// The output is either a standalone single page document with [`PdfCanvas::finish`], or a form
+/// XObject to embed in another document with [`PdfCanvas::finish_form_xobject`]. In both cases
+/// the origin of the PDF coordinate system is on the baseline, at the left of the equation.
+pub struct PdfCanvas {
+    content: String,
+    /// alpha values used, each needs a graphics state in the resources
+    alphas: BTreeSet<u8>,
+}
+
+impl Default for PdfCanvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfCanvas {
+    pub fn new() -> Self {
+        Self {
+            content: String::new(),
+            alphas: BTreeSet::new(),
+        }
+    }
+
+    /// Write a single page PDF document of the size of the rendered element.
+    ///
+    /// The media box spans from `-descent` to `ascent` vertically, so the baseline is at `y = 0`.
+    pub fn finish(self, width: f32, ascent: f32, descent: f32) -> Vec<u8> {
+        let content = self.content_stream(ascent);
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox {} /Resources {} /Contents 4 0 R >>",
+                bounding_box(width, ascent, descent),
+                self.resources()
+            ),
+            stream("", &content),
+        ];
+
+        // The binary comment marks the file as binary for transfer programs
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = vec![];
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+        }
+        let xref_offset = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            // Each entry must be exactly 20 bytes long, including the end of line
+            writeln!(trailer, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .unwrap();
+        pdf.extend_from_slice(trailer.as_bytes());
+        pdf
+    }
+
+    /// Write a form XObject (the dictionary and stream, without the `obj` header) of the size of
+    /// the rendered element, to be embedded in another document.
+    ///
+    /// Its bounding box spans from `-descent` to `ascent` vertically, so painting it with
+    /// `1 0 0 1 x y cm /Name Do` puts the baseline of the equation at `y`.
+    pub fn finish_form_xobject(self, width: f32, ascent: f32, descent: f32) -> String {
+        let content = self.content_stream(ascent);
+        stream(
+            &format!(
+                "/Type /XObject /Subtype /Form /BBox {} /Resources {} ",
+                bounding_box(width, ascent, descent),
+                self.resources()
+            ),
+            &content,
+        )
+    }
+
+    /// Recorded operators, flipped to the y up coordinate system of PDF with the baseline at 0
+    fn content_stream(&self, ascent: f32) -> String {
+        format!("q 1 0 0 -1 0 {} cm\n{}Q", number(ascent), self.content)
+    }
+
+    fn resources(&self) -> String {
+        let mut resources = String::from("<<");
+        if !self.alphas.is_empty() {
+            resources.push_str(" /ExtGState <<");
+            for alpha in &self.alphas {
+                let opacity = number(f32::from(*alpha) / 255.0);
+                write!(resources, " /A{alpha} << /ca {opacity} /CA {opacity} >>").unwrap();
+            }
+            resources.push_str(" >>");
+        }
+        resources.push_str(" >>");
+        resources
+    }
+
+    /// Set the fill (`rg`) or stroke (`RG`) color, and the opacity if needed
+    fn set_color(&mut self, operator: &str, color: Color) {
+        let Color { r, g, b, a } = color;
+        if a != 255 {
+            self.alphas.insert(a);
+            writeln!(self.content, "/A{a} gs").unwrap();
+        }
+        writeln!(
+            self.content,
+            "{} {} {} {operator}",
+            number(f32::from(r) / 255.0),
+            number(f32::from(g) / 255.0),
+            number(f32::from(b) / 255.0)
+        )
+        .unwrap();
+    }
+
+    fn append_path(&mut self, path: &Path) {
+        // Quadratic curves are elevated to cubic ones, which needs the current point
+        let mut current = (0.0, 0.0);
+        let mut start = (0.0, 0.0);
+        for command in path.commands() {
+            match *command {
+                PathCommand::MoveTo(x, y) => {
+                    writeln!(self.content, "{} {} m", number(x), number(y)).unwrap();
+                    current = (x, y);
+                    start = (x, y);
+                }
+                PathCommand::LineTo(x, y) => {
+                    writeln!(self.content, "{} {} l", number(x), number(y)).unwrap();
+                    current = (x, y);
+                }
+                PathCommand::QuadTo(cx, cy, x, y) => {
+                    let (x0, y0) = current;
+                    writeln!(
+                        self.content,
+                        "{} {} {} {} {} {} c",
+                        number(x0 + 2.0 / 3.0 * (cx - x0)),
+                        number(y0 + 2.0 / 3.0 * (cy - y0)),
+                        number(x + 2.0 / 3.0 * (cx - x)),
+                        number(y + 2.0 / 3.0 * (cy - y)),
+                        number(x),
+                        number(y)
+                    )
+                    .unwrap();
+                    current = (x, y);
+                }
+                PathCommand::CurveTo(cx0, cy0, cx1, cy1, x, y) => {
+                    writeln!(
+                        self.content,
+                        "{} {} {} {} {} {} c",
+                        number(cx0),
+                        number(cy0),
+                        number(cx1),
+                        number(cy1),
+                        number(x),
+                        number(y)
+                    )
+                    .unwrap();
+                    current = (x, y);
+                }
+                PathCommand::Close => {
+                    self.content.push_str("h\n");
+                    current = start;
+                }
+            }
+        }
+    }
+}
+
+impl MathCanvas for PdfCanvas {
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        if path.is_empty() {
+            return;
+        }
+        self.content.push_str("q\n");
+        self.set_color("rg", color);
+        self.append_path(path);
+        self.content.push_str("f\nQ\n");
+    }
+
+    fn stroke_path(&mut self, path: &Path, width: f32, color: Color) {
+        if path.is_empty() {
+            return;
+        }
+        self.content.push_str("q\n");
+        self.set_color("RG", color);
+        writeln!(self.content, "{} w", number(width)).unwrap();
+        self.append_path(path);
+        self.content.push_str("S\nQ\n");
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        self.content.push_str("q\n");
+        self.set_color("rg", color);
+        writeln!(
+            self.content,
+            "{} {} {} {} re\nf\nQ",
+            number(x),
+            number(y),
+            number(width),
+            number(height)
+        )
+        .unwrap();
+    }
+}
+
+fn bounding_box(width: f32, ascent: f32, descent: f32) -> String {
+    format!(
+        "[0 {} {} {}]",
+        number(-descent),
+        number(width),
+        number(ascent)
+    )
+}
+
+/// Stream object with extra dictionary entries
+fn stream(entries: &str, content: &str) -> String {
+    format!(
+        "<< {entries}/Length {} >>\nstream\n{content}\nendstream",
+        content.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mml_types::{mfrac, mi, mn, mrow};
+    use crate::render::Render;
+    use crate::style::StyleContext;
+    use crate::text_rendering::TextRenderer;
+
+    #[test]
+    fn fraction_pdf() {
+        let pdf = mfrac(mrow(vec![mi("x"), mi("x")]), mn("2"))
+            .render_pdf(&mut TextRenderer::new(), &StyleContext::new(100.0));
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/MediaBox [0 -"));
+        // the bar of the fraction
+        assert_eq!(text.matches(" re\n").count(), 1);
+
+        // The cross reference table points at each object, offsets are in bytes
+        let startxref = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref_offset: usize = text[startxref..].lines().next().unwrap().parse().unwrap();
+        let xref = String::from_utf8(pdf[xref_offset..].to_vec()).unwrap();
+        assert!(xref.starts_with("xref\n0 5\n"));
+        for (index, entry) in xref.lines().skip(3).take(4).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn form_xobject() {
+        let mut canvas = PdfCanvas::new();
+        canvas.fill_rect(0.0, 0.0, 10.0, 2.0, Color::from_rgba8(255, 0, 0, 128));
+        let xobject = canvas.finish_form_xobject(10.0, 1.5, 0.5);
+        assert!(xobject.starts_with("<< /Type /XObject /Subtype /Form /BBox [0 -0.5 10 1.5]"));
+        assert!(xobject.contains("/ExtGState << /A128 << /ca 0.5 /CA 0.5 >> >>"));
+        assert!(xobject.contains("/A128 gs\n1 0 0 rg\n0 0 10 2 re\n"));
+        assert!(xobject.ends_with("endstream"));
+    }
+}
@@ -1,329 +1,1263 @@
-#[allow(unused)]
-#[allow(dead_code)]
-use std::cmp::Ordering;
-
+use crate::canvas::{MathCanvas, Path};
+use crate::math_table::Corner;
+use crate::math_variant::styled_text;
 use crate::mml_types::{
-    Element, Mfrac, Mi, Mn, Mo, Mphantom, Mroot, Mrow, Msqrt, Msub, Msup, Mtext,
+    ColumnAlign, Element, Form, Length, LineThickness, Math, MathVariant, Menclose, Mfrac, Mi,
+    Mmultiscripts, Mn, Mo, Mover, Mpadded, Mphantom, Mroot, Mrow, Mspace, Msqrt, Mstyle, Msub,
+    Msubsup, Msup, Mtext, Munder, Munderover, Notation, PaddedLength, PaddedValue, PseudoUnit,
+    ScriptLevel, ScriptPair,
 };
-use crate::mml_types::{mfrac, mi, mn, mo, mroot, mrow, msub, msup, mtext};
-use crate::text_rendering::{TextRenderer, render_text};
-use tiny_skia::{FillRule, IntRect, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke, Transform};
+use crate::operator_dictionary::{OperatorProperties, operator_properties};
+use crate::pdf::PdfCanvas;
+use crate::stretchy::{display_variant, plan_large_operator, plan_stretched_operator};
+use crate::style::StyleContext;
+use crate::svg::SvgCanvas;
+use crate::text_rendering::TextRenderer;
+use std::fmt;
+use std::path::PathBuf;
+use tiny_skia::Pixmap;
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// The font family name could not be parsed
+    InvalidFontFamily(String),
+    /// No font of this family is installed or registered
+    FontNotFound(String),
+    /// The font file could not be read
+    FontFile {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The font data doesn't contain any font
+    InvalidFontData,
+    /// The rendered element has no area to draw on, e.g. an empty `<mrow>`
+    EmptyCanvas { width: u32, height: u32 },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::InvalidFontFamily(family) => write!(f, "invalid font family: {family}"),
+            RenderError::FontNotFound(family) => write!(f, "font family not found: {family}"),
+            RenderError::FontFile { path, error } => {
+                write!(f, "cannot read font file {}: {error}", path.display())
+            }
+            RenderError::InvalidFontData => write!(f, "no font found in the font data"),
+            RenderError::EmptyCanvas { width, height } => {
+                write!(f, "cannot render onto a {width}x{height} canvas")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::FontFile { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// callback to render element on the canvas with its top left corner at the position (x, y).
+/// arguments: canvas, x, y
+pub type RenderCallback = Box<dyn Fn(&mut dyn MathCanvas, f32, f32)>;
 
 pub struct RenderingPlan {
-    /// callback to render element on the pixmap at the position (x, y).
-    /// arguments: text_renderer, pixmap, x, y, font_size
-    pub callback: Box<dyn Fn(&mut TextRenderer, &mut Pixmap, u32, u32) -> ()>,
-    /// vertical position on the element where the horizontal line of the '+' symbol should be aligned with
-    pub baseline: u32,
-    pub width: u32,
-    pub height: u32,
+    pub callback: RenderCallback,
+    /// distance from the top of the element to its alphabetic baseline
+    pub ascent: f32,
+    /// distance from the alphabetic baseline to the bottom of the element
+    pub descent: f32,
+    pub width: f32,
+}
+
+impl RenderingPlan {
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+
+    /// Draw the element on any canvas, with its top left corner at (x, y)
+    pub fn draw(&self, canvas: &mut dyn MathCanvas, x: f32, y: f32) {
+        (self.callback)(canvas, x, y);
+    }
 }
 
 pub trait Render {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan;
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError>;
+
+    /// Panicking version of [`Render::try_plan_render`]
+    fn plan_render(&self, text_renderer: &mut TextRenderer, style: &StyleContext) -> RenderingPlan {
+        self.try_plan_render(text_renderer, style)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
 
-    fn render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> Pixmap {
-        let RenderingPlan {
-            callback,
-            baseline,
-            width,
-            height,
-        } = self.plan_render(text_renderer, font_size);
-        let mut pixmap = Pixmap::new(width, height).unwrap();
-        callback(text_renderer, &mut pixmap, 0, 0);
+    fn try_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<Pixmap, RenderError> {
+        let plan = self.try_plan_render(text_renderer, style)?;
+        // layout is done with fractional pixels, only the canvas is rounded
+        let width = plan.width.ceil() as u32;
+        let height = plan.height().ceil() as u32;
+        let mut pixmap =
+            Pixmap::new(width, height).ok_or(RenderError::EmptyCanvas { width, height })?;
+        plan.draw(&mut pixmap, 0.0, 0.0);
+
+        Ok(pixmap)
+    }
+
+    /// Panicking version of [`Render::try_render`]
+    fn render(&self, text_renderer: &mut TextRenderer, style: &StyleContext) -> Pixmap {
+        self.try_render(text_renderer, style)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Render as a standalone `<svg>` element, see [`SvgCanvas`] to customise the output
+    fn try_render_svg(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<String, RenderError> {
+        let plan = self.try_plan_render(text_renderer, style)?;
+        let mut canvas = SvgCanvas::new();
+        plan.draw(&mut canvas, 0.0, 0.0);
+
+        Ok(canvas.finish(plan.width, plan.ascent, plan.descent))
+    }
+
+    /// Panicking version of [`Render::try_render_svg`]
+    fn render_svg(&self, text_renderer: &mut TextRenderer, style: &StyleContext) -> String {
+        self.try_render_svg(text_renderer, style)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
 
-        pixmap
+    /// Render as a standalone single page PDF document, see [`PdfCanvas`] to embed it instead
+    fn try_render_pdf(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<Vec<u8>, RenderError> {
+        let plan = self.try_plan_render(text_renderer, style)?;
+        let mut canvas = PdfCanvas::new();
+        plan.draw(&mut canvas, 0.0, 0.0);
+
+        Ok(canvas.finish(plan.width, plan.ascent, plan.descent))
+    }
+
+    /// Panicking version of [`Render::try_render_pdf`]
+    fn render_pdf(&self, text_renderer: &mut TextRenderer, style: &StyleContext) -> Vec<u8> {
+        self.try_render_pdf(text_renderer, style)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl Mi {
+    /// Identifier as it is drawn, with its `mathvariant` applied
+    fn styled_identifier(&self, text_renderer: &TextRenderer, style: &StyleContext) -> String {
+        // Identifiers of a single character are italic by default
+        let variant = self.mathvariant.or(style.mathvariant).unwrap_or(
+            if self.identifier.chars().count() == 1 {
+                MathVariant::Italic
+            } else {
+                MathVariant::Normal
+            },
+        );
+        styled_text(text_renderer, &self.identifier, variant)
     }
 }
 
 impl Render for Mi {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        text_renderer.plan_render_text(self.identifier.clone(), font_size)
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let identifier = self.styled_identifier(text_renderer, style);
+        text_renderer.plan_render_text(identifier, style)
     }
 }
 
 impl Render for Mtext {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        text_renderer.plan_render_text(self.text.clone(), font_size)
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let text = variant_text(text_renderer, &self.text, self.mathvariant, style);
+        text_renderer.plan_render_text(text, style)
     }
 }
 
 impl Render for Mn {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        text_renderer.plan_render_text(self.number.clone(), font_size)
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let number = variant_text(text_renderer, &self.number, self.mathvariant, style);
+        text_renderer.plan_render_text(number, style)
     }
 }
-impl Render for Mo {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let spacing = (font_size / 10.0).floor() as u32;
+
+/// Text of a token styled by its `mathvariant`, or else the inherited one, kept as it is when
+/// neither is given
+fn variant_text(
+    text_renderer: &TextRenderer,
+    text: &str,
+    mathvariant: Option<MathVariant>,
+    style: &StyleContext,
+) -> String {
+    match mathvariant.or(style.mathvariant) {
+        Some(variant) => styled_text(text_renderer, text, variant),
+        None => text.into(),
+    }
+}
+impl Mo {
+    /// Properties of the operator in the given form (unless it has an explicit `form`), from the
+    /// operator dictionary and overridden by its attributes
+    pub fn properties(&self, form: Form) -> OperatorProperties {
+        let form = self.form.unwrap_or(form);
+        let properties = operator_properties(&self.operator, form);
+        OperatorProperties {
+            lspace: self.lspace.unwrap_or(properties.lspace),
+            rspace: self.rspace.unwrap_or(properties.rspace),
+            stretchy: self.stretchy.unwrap_or(properties.stretchy),
+            symmetric: self.symmetric.unwrap_or(properties.symmetric),
+            largeop: self.largeop.unwrap_or(properties.largeop),
+            movablelimits: self.movablelimits.unwrap_or(properties.movablelimits),
+            fence: self.fence.unwrap_or(properties.fence),
+            separator: self.separator.unwrap_or(properties.separator),
+            horizontal: properties.horizontal,
+        }
+    }
+
+    /// Plan the operator in the given form, see [`Mo::properties`].
+    ///
+    /// If it stretches vertically, it is stretched to cover `stretch_to` (ascent and descent).
+    pub fn try_plan_with_form(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+        form: Form,
+        stretch_to: Option<(f32, f32)>,
+    ) -> Result<RenderingPlan, RenderError> {
+        let properties = self.properties(form);
+        let plan = match stretch_to {
+            Some((ascent, descent)) if properties.stretchy && !properties.horizontal => {
+                plan_stretched_operator(
+                    text_renderer,
+                    &self.operator,
+                    style,
+                    ascent,
+                    descent,
+                    properties.symmetric,
+                )?
+            }
+            // Large operators are larger in display style, without stretching to their operands
+            _ if properties.largeop && style.displaystyle => {
+                plan_large_operator(text_renderer, &self.operator, style)?
+            }
+            _ => {
+                let operator = variant_text(text_renderer, &self.operator, self.mathvariant, style);
+                text_renderer.plan_render_text(operator, style)?
+            }
+        };
+        let x_height = text_renderer.x_height() * style.font_size;
+        let lspace = properties.lspace.to_px(style.font_size, x_height);
+        let rspace = properties.rspace.to_px(style.font_size, x_height);
+
         let RenderingPlan {
             callback,
-            baseline,
+            ascent,
+            descent,
             width,
-            height,
-        } = text_renderer.plan_render_text(self.operator.clone(), font_size);
-        let width = width + 2 * spacing;
-        let callback =
-            move |text_renderer: &mut TextRenderer, pixmap: &mut Pixmap, x: u32, y: u32| {
-                callback(text_renderer, pixmap, x + spacing, y);
-            };
-        RenderingPlan {
+        } = plan;
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            callback(canvas, x + lspace, y);
+        };
+        Ok(RenderingPlan {
             callback: Box::new(callback),
-            baseline,
-            width,
-            height,
+            ascent,
+            descent,
+            width: lspace + width + rspace,
+        })
+    }
+}
+
+impl Render for Mo {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        self.try_plan_with_form(text_renderer, style, Form::Infix, None)
+    }
+}
+
+/// Properties of an operator which is the base of scripts or limits (e.g. `∑` or `∫`), looked up
+/// in the prefix form such operators have in the dictionary
+fn base_operator_properties(base: &Element) -> Option<OperatorProperties> {
+    match base {
+        Element::Mo(mo) => Some(mo.properties(Form::Prefix)),
+        _ => None,
+    }
+}
+
+/// Glyph of the math font of an element drawn as a single character, e.g. `<mi>f</mi>` or
+/// `<mo>∫</mo>`, whose italic correction and kerning apply to the scripts attached to it
+fn single_glyph(
+    element: &Element,
+    text_renderer: &TextRenderer,
+    style: &StyleContext,
+) -> Option<u32> {
+    let text = match element {
+        Element::Mi(mi) => mi.styled_identifier(text_renderer, style),
+        Element::Mo(mo) => {
+            // The larger glyph drawn in display style, see `plan_large_operator`
+            let largeop = base_operator_properties(element).is_some_and(|base| base.largeop);
+            if largeop && style.displaystyle {
+                if let Some(variant) = display_variant(text_renderer, &mo.operator) {
+                    return Some(variant);
+                }
+            }
+            variant_text(text_renderer, &mo.operator, mo.mathvariant, style)
         }
+        _ => return None,
+    };
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(character), None) => text_renderer.math_glyph_id(character),
+        _ => None,
     }
 }
-impl Render for Msup {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let paint = PixmapPaint::default();
-        let transform = Transform::default();
-        const SUPERSCRIPT_FONT_RATIO: f32 = 0.7;
-        const SUPERSCRIPT_VERTICAL_OFFSET: f32 = 0.5;
-        // let (base, y)
+
+/// Pairs of (subscript, superscript), either of which may be missing
+type ScriptPairs<'a> = Vec<(Option<&'a Element>, Option<&'a Element>)>;
+
+/// Base with pairs of scripts after and before it, shared by all the elements with scripts on
+/// the side of their base. All the subscripts share one baseline, and the superscripts another.
+struct _Mmultiscripts<'a> {
+    base: &'a Element,
+    postscripts: ScriptPairs<'a>,
+    prescripts: ScriptPairs<'a>,
+}
+
+impl Render for _Mmultiscripts<'_> {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let constants = text_renderer.math_constants();
+        let script_style = style.script(constants);
+        let sub_shift_down = constants.subscript_shift_down * font_size;
+        let sub_baseline_drop_min = constants.subscript_baseline_drop_min * font_size;
+        let sub_top_max = constants.subscript_top_max * font_size;
+        let sup_shift_up = constants.superscript_shift_up * font_size;
+        let sup_baseline_drop_max = constants.superscript_baseline_drop_max * font_size;
+        let sup_bottom_min = constants.superscript_bottom_min * font_size;
+        let sup_bottom_max_with_sub = constants.superscript_bottom_max_with_subscript * font_size;
+        let gap_min = constants.sub_superscript_gap_min * font_size;
+        let space_after_script = constants.space_after_script * font_size;
         let RenderingPlan {
             callback: base_callback,
-            baseline: base_baseline,
+            ascent: base_ascent,
+            descent: base_descent,
             width: base_width,
-            height: base_height,
-        } = self.base.plan_render(text_renderer, font_size);
-        // let (superscript, _)
-        let RenderingPlan {
-            callback: superscript_callback,
-            baseline: superscript_baseline,
-            width: superscript_width,
-            height: superscript_height,
-        } = self
-            .superscript
-            .plan_render(text_renderer, font_size * SUPERSCRIPT_FONT_RATIO);
-
-        let width = base_width + superscript_width;
-        let height = base_height.max(superscript_height * 2);
-        let base_y_offset = 0.max(superscript_height - base_height / 2);
-        let baseline = base_baseline + base_y_offset;
-
-        let callback =
-            move |text_renderer: &mut TextRenderer, pixmap: &mut Pixmap, x: u32, y: u32| {
-                base_callback(text_renderer, pixmap, x, y + base_y_offset);
-                superscript_callback(text_renderer, pixmap, x + base_width, y);
+        } = self.base.try_plan_render(text_renderer, style)?;
+        let mut plan_pairs = |pairs: &ScriptPairs| {
+            pairs
+                .iter()
+                .map(|&(subscript, superscript)| {
+                    let mut plan_script = |script: Option<&Element>| {
+                        script
+                            .map(|script| script.try_plan_render(text_renderer, &script_style))
+                            .transpose()
+                    };
+                    Ok((plan_script(subscript)?, plan_script(superscript)?))
+                })
+                .collect::<Result<Vec<_>, RenderError>>()
+        };
+        let postscripts = plan_pairs(&self.postscripts)?;
+        let prescripts = plan_pairs(&self.prescripts)?;
+        let pairs = || prescripts.iter().chain(&postscripts);
+        let base_glyph = single_glyph(self.base, text_renderer, style);
+        let script_glyphs = |pair: Option<&(Option<&Element>, Option<&Element>)>| {
+            let glyph = |script: Option<&Element>| {
+                script.and_then(|script| single_glyph(script, text_renderer, &script_style))
             };
+            pair.map_or((None, None), |&(subscript, superscript)| {
+                (glyph(subscript), glyph(superscript))
+            })
+        };
+        // Only the scripts next to the base are kerned against it
+        let postscript_glyphs = script_glyphs(self.postscripts.first());
+        let prescript_glyphs = script_glyphs(self.prescripts.last());
+
+        // distances between the baselines of the base and the scripts, as far as needed by the
+        // largest script
+        let mut shift_down = pairs()
+            .filter_map(|(subscript, _)| subscript.as_ref())
+            .map(|subscript| {
+                sub_shift_down
+                    .max(base_descent + sub_baseline_drop_min)
+                    .max(subscript.ascent - sub_top_max)
+            })
+            .fold(0.0, f32::max);
+        let mut shift_up = pairs()
+            .filter_map(|(_, superscript)| superscript.as_ref())
+            .map(|superscript| {
+                sup_shift_up
+                    .max(base_ascent - sup_baseline_drop_max)
+                    .max(sup_bottom_min + superscript.descent)
+            })
+            .fold(0.0, f32::max);
+
+        // Separate the scripts of each pair, by raising the superscripts as far as allowed, then
+        // lowering the subscripts
+        for (subscript, superscript) in pairs() {
+            let (Some(subscript), Some(superscript)) = (subscript, superscript) else {
+                continue;
+            };
+            let gap = (shift_up - superscript.descent) - (subscript.ascent - shift_down);
+            if gap < gap_min {
+                let raise = (sup_bottom_max_with_sub - (shift_up - superscript.descent))
+                    .min(gap_min - gap)
+                    .max(0.0);
+                shift_up += raise;
+                shift_down += gap_min - gap - raise;
+            }
+        }
 
-        RenderingPlan {
+        let mut ascent = base_ascent;
+        let mut descent = base_descent;
+        for (subscript, superscript) in pairs() {
+            if let Some(subscript) = subscript {
+                ascent = ascent.max(subscript.ascent - shift_down);
+                descent = descent.max(shift_down + subscript.descent);
+            }
+            if let Some(superscript) = superscript {
+                ascent = ascent.max(shift_up + superscript.ascent);
+                descent = descent.max(superscript.descent - shift_up);
+            }
+        }
+
+        // The scripts next to the base move into the cut-ins at its corners, by the larger of
+        // the kerns at the two heights where the shapes of the base and the script meet
+        let glyph_info = text_renderer.math_glyph_info();
+        let script_size = script_style.font_size;
+        let kern = |script: &RenderingPlan,
+                    script_glyph: Option<u32>,
+                    [base_corner, script_corner]: [Corner; 2],
+                    superscript: bool| {
+            let (baseline, heights) = if superscript {
+                (shift_up, [shift_up - script.descent, base_ascent])
+            } else {
+                (-shift_down, [script.ascent - shift_down, -base_descent])
+            };
+            heights
+                .into_iter()
+                .map(|height| {
+                    let base_kern = base_glyph.map_or(0.0, |glyph| {
+                        glyph_info.kern(glyph, base_corner, height / font_size) * font_size
+                    });
+                    let script_kern = script_glyph.map_or(0.0, |glyph| {
+                        let height = (height - baseline) / script_size;
+                        glyph_info.kern(glyph, script_corner, height) * script_size
+                    });
+                    base_kern + script_kern
+                })
+                .fold(f32::NEG_INFINITY, f32::max)
+        };
+        // Superscripts are placed after the italic correction of a slanted base, and subscripts
+        // of large operators (whose advance includes it) move back by it
+        let italic_correction = base_glyph.map_or(0.0, |glyph| {
+            glyph_info.italics_correction(glyph) * font_size
+        });
+        let largeop = base_operator_properties(self.base).is_some_and(|base| base.largeop);
+        let post_offsets = postscripts
+            .first()
+            .map_or((0.0, 0.0), |(subscript, superscript)| {
+                let (sub_glyph, sup_glyph) = postscript_glyphs;
+                let sub_corners = [Corner::BottomRight, Corner::TopLeft];
+                let sup_corners = [Corner::TopRight, Corner::BottomLeft];
+                (
+                    subscript.as_ref().map_or(0.0, |subscript| {
+                        let correction = if largeop { -italic_correction } else { 0.0 };
+                        correction + kern(subscript, sub_glyph, sub_corners, false)
+                    }),
+                    superscript.as_ref().map_or(0.0, |superscript| {
+                        italic_correction + kern(superscript, sup_glyph, sup_corners, true)
+                    }),
+                )
+            });
+        let pre_offsets = prescripts
+            .last()
+            .map_or((0.0, 0.0), |(subscript, superscript)| {
+                let (sub_glyph, sup_glyph) = prescript_glyphs;
+                let sub_corners = [Corner::BottomLeft, Corner::TopRight];
+                let sup_corners = [Corner::TopLeft, Corner::BottomRight];
+                (
+                    subscript.as_ref().map_or(0.0, |subscript| {
+                        kern(subscript, sub_glyph, sub_corners, false)
+                    }),
+                    superscript.as_ref().map_or(0.0, |superscript| {
+                        kern(superscript, sup_glyph, sup_corners, true)
+                    }),
+                )
+            });
+
+        // Scripts with their horizontal position and the height of their baseline. The scripts
+        // of a pair are aligned towards the base, and offset away from it.
+        let mut scripts = vec![];
+        let mut x = 0.0;
+        let mut place_pair =
+            |(subscript, superscript): (Option<RenderingPlan>, Option<RenderingPlan>),
+             x: f32,
+             (sub_offset, sup_offset): (f32, f32),
+             prescript: bool| {
+                let pair_width = [(&subscript, sub_offset), (&superscript, sup_offset)]
+                    .into_iter()
+                    .filter_map(|(script, offset)| Some(script.as_ref()?.width + offset))
+                    .fold(0.0, f32::max);
+                for (script, shift, offset) in [
+                    (subscript, -shift_down, sub_offset),
+                    (superscript, shift_up, sup_offset),
+                ] {
+                    if let Some(script) = script {
+                        let script_x = if prescript {
+                            x + pair_width - script.width - offset
+                        } else {
+                            x + offset
+                        };
+                        scripts.push((script, script_x, shift));
+                    }
+                }
+                pair_width
+            };
+        let prescript_count = prescripts.len();
+        for (index, pair) in prescripts.into_iter().enumerate() {
+            let offsets = if index + 1 == prescript_count {
+                pre_offsets
+            } else {
+                (0.0, 0.0)
+            };
+            x += space_after_script;
+            x += place_pair(pair, x, offsets, true);
+        }
+        let base_x = x;
+        x += base_width;
+        for (index, pair) in postscripts.into_iter().enumerate() {
+            let offsets = if index == 0 { post_offsets } else { (0.0, 0.0) };
+            x += place_pair(pair, x, offsets, false) + space_after_script;
+        }
+        let width = x;
+
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            base_callback(canvas, x + base_x, y + ascent - base_ascent);
+            for (script, script_x, shift) in &scripts {
+                script.draw(canvas, x + script_x, y + ascent - shift - script.ascent);
+            }
+        };
+
+        Ok(RenderingPlan {
             callback: Box::new(callback),
-            baseline,
+            ascent,
+            descent,
             width,
-            height,
+        })
+    }
+}
+
+impl Render for Msup {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        _Mmultiscripts {
+            base: &self.base,
+            postscripts: vec![(None, Some(&self.superscript))],
+            prescripts: vec![],
         }
+        .try_plan_render(text_renderer, style)
     }
 }
 impl Render for Msub {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let paint = PixmapPaint::default();
-        let transform = Transform::default();
-        const SUBSCRIPT_FONT_RATIO: f32 = 0.7;
-        const SUPERSCRIPT_VERTICAL_OFFSET: f32 = 0.5;
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        _Mmultiscripts {
+            base: &self.base,
+            postscripts: vec![(Some(&self.subscript), None)],
+            prescripts: vec![],
+        }
+        .try_plan_render(text_renderer, style)
+    }
+}
+impl Render for Msubsup {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        _Mmultiscripts {
+            base: &self.base,
+            postscripts: vec![(Some(&self.subscript), Some(&self.superscript))],
+            prescripts: vec![],
+        }
+        .try_plan_render(text_renderer, style)
+    }
+}
+impl Render for Mmultiscripts {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        fn pairs(pairs: &[ScriptPair]) -> ScriptPairs<'_> {
+            pairs
+                .iter()
+                .map(|pair| (pair.subscript.as_ref(), pair.superscript.as_ref()))
+                .collect()
+        }
+        _Mmultiscripts {
+            base: &self.base,
+            postscripts: pairs(&self.postscripts),
+            prescripts: pairs(&self.prescripts),
+        }
+        .try_plan_render(text_renderer, style)
+    }
+}
+
+/// Base with scripts centered under and/or over it, shared by munder, mover and munderover
+struct _Munderover<'a> {
+    base: &'a Element,
+    underscript: Option<&'a Element>,
+    overscript: Option<&'a Element>,
+    accentunder: bool,
+    accent: bool,
+}
+
+impl Render for _Munderover<'_> {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        // Limits of operators such as sums are moved to the scripts positions in inline style
+        let movable_limits =
+            base_operator_properties(self.base).is_some_and(|base| base.movablelimits);
+        if movable_limits && !style.displaystyle {
+            return _Mmultiscripts {
+                base: self.base,
+                postscripts: vec![(self.underscript, self.overscript)],
+                prescripts: vec![],
+            }
+            .try_plan_render(text_renderer, style);
+        }
+
+        let constants = text_renderer.math_constants();
+        let script = style.script(constants);
+        let accent_base_height = constants.accent_base_height * font_size;
+        let upper_gap_min = constants.upper_limit_gap_min * font_size;
+        let upper_baseline_rise_min = constants.upper_limit_baseline_rise_min * font_size;
+        let lower_gap_min = constants.lower_limit_gap_min * font_size;
+        let lower_baseline_drop_min = constants.lower_limit_baseline_drop_min * font_size;
         let RenderingPlan {
             callback: base_callback,
-            baseline: base_baseline,
+            ascent: base_ascent,
+            descent: base_descent,
             width: base_width,
-            height: base_height,
-        } = self.base.plan_render(text_renderer, font_size);
-        // let (superscript, _)
-        let RenderingPlan {
-            callback: subscript_callback,
-            baseline: subscript_baseline,
-            width: subscript_width,
-            height: subscript_height,
-        } = self
-            .subscript
-            .plan_render(text_renderer, font_size * SUBSCRIPT_FONT_RATIO);
-
-        let width = base_width + subscript_width;
-        let height = base_height.max(2 * subscript_height);
-        let mut pixmap = Pixmap::new(width, height).unwrap();
-
-        let base_y_offset = 0.max(subscript_height - base_height / 2);
-        let subscript_y_offset = base_y_offset + (base_height / 2);
-        let baseline = base_baseline + base_y_offset;
-
-        let callback =
-            move |text_renderer: &mut TextRenderer, pixmap: &mut Pixmap, x: u32, y: u32| {
-                base_callback(text_renderer, pixmap, x, y + base_y_offset);
-                subscript_callback(
-                    text_renderer,
-                    pixmap,
-                    x + base_width,
-                    y + subscript_y_offset,
+        } = self.base.try_plan_render(text_renderer, style)?;
+        // Accents keep the size of the base, other scripts are drawn at script size
+        let script_style = |accent: bool| {
+            if accent {
+                StyleContext {
+                    displaystyle: false,
+                    ..style.child()
+                }
+            } else {
+                script
+            }
+        };
+        let underscript = self
+            .underscript
+            .map(|underscript| {
+                underscript.try_plan_render(text_renderer, &script_style(self.accentunder))
+            })
+            .transpose()?;
+        let overscript = self
+            .overscript
+            .map(|overscript| overscript.try_plan_render(text_renderer, &script_style(self.accent)))
+            .transpose()?;
+
+        // distances between the baselines of the base and the scripts
+        let shift_down = underscript.as_ref().map_or(0.0, |underscript| {
+            if self.accentunder {
+                base_descent + underscript.ascent
+            } else {
+                base_descent + (lower_gap_min + underscript.ascent).max(lower_baseline_drop_min)
+            }
+        });
+        let shift_up = overscript.as_ref().map_or(0.0, |overscript| {
+            if self.accent {
+                // Accent glyphs are designed to sit on bases as tall as AccentBaseHeight
+                (base_ascent - accent_base_height).max(0.0)
+            } else {
+                base_ascent + (upper_gap_min + overscript.descent).max(upper_baseline_rise_min)
+            }
+        });
+
+        let width = [&underscript, &overscript]
+            .into_iter()
+            .flatten()
+            .map(|script| script.width)
+            .fold(base_width, f32::max);
+        let mut ascent = base_ascent;
+        let mut descent = base_descent;
+        if let Some(underscript) = &underscript {
+            ascent = ascent.max(underscript.ascent - shift_down);
+            descent = descent.max(shift_down + underscript.descent);
+        }
+        if let Some(overscript) = &overscript {
+            ascent = ascent.max(shift_up + overscript.ascent);
+            descent = descent.max(overscript.descent - shift_up);
+        }
+
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            base_callback(
+                canvas,
+                x + (width - base_width) / 2.0,
+                y + ascent - base_ascent,
+            );
+            if let Some(overscript) = &overscript {
+                overscript.draw(
+                    canvas,
+                    x + (width - overscript.width) / 2.0,
+                    y + ascent - shift_up - overscript.ascent,
                 );
-            };
+            }
+            if let Some(underscript) = &underscript {
+                underscript.draw(
+                    canvas,
+                    x + (width - underscript.width) / 2.0,
+                    y + ascent + shift_down - underscript.ascent,
+                );
+            }
+        };
 
-        RenderingPlan {
+        Ok(RenderingPlan {
             callback: Box::new(callback),
-            baseline,
+            ascent,
+            descent,
             width,
-            height,
+        })
+    }
+}
+
+impl Render for Munder {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        _Munderover {
+            base: &self.base,
+            underscript: Some(&self.underscript),
+            overscript: None,
+            accentunder: self.accentunder.unwrap_or(false),
+            accent: false,
         }
+        .try_plan_render(text_renderer, style)
     }
 }
+impl Render for Mover {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        _Munderover {
+            base: &self.base,
+            underscript: None,
+            overscript: Some(&self.overscript),
+            accentunder: false,
+            accent: self.accent.unwrap_or(false),
+        }
+        .try_plan_render(text_renderer, style)
+    }
+}
+impl Render for Munderover {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        _Munderover {
+            base: &self.base,
+            underscript: Some(&self.underscript),
+            overscript: Some(&self.overscript),
+            accentunder: self.accentunder.unwrap_or(false),
+            accent: self.accent.unwrap_or(false),
+        }
+        .try_plan_render(text_renderer, style)
+    }
+}
+/// Plan terms side by side on their baseline, with stretchy operators as tall as the others
+fn plan_row(
+    terms: &[Element],
+    text_renderer: &mut TextRenderer,
+    style: &StyleContext,
+) -> Result<RenderingPlan, RenderError> {
+    // The form of operators is inferred from their position in the row
+    let count = terms.len();
+    let form = |index: usize| match index {
+        0 if count > 1 => Form::Prefix,
+        _ if count > 1 && index == count - 1 => Form::Postfix,
+        _ => Form::Infix,
+    };
+    let stretches = |mo: &Mo, form| {
+        let properties = mo.properties(form);
+        properties.stretchy && !properties.horizontal
+    };
+
+    // Stretchy operators are planned once the size of the other terms is known
+    let mut plans = vec![];
+    for (index, child) in terms.iter().enumerate() {
+        plans.push(match child {
+            Element::Mo(mo) if stretches(mo, form(index)) => None,
+            Element::Mo(mo) => {
+                Some(mo.try_plan_with_form(text_renderer, style, form(index), None)?)
+            }
+            _ => Some(child.try_plan_render(text_renderer, style)?),
+        });
+    }
+    let stretch_to =
+        plans
+            .iter()
+            .flatten()
+            .fold((0.0, 0.0), |(ascent, descent): (f32, f32), child| {
+                (ascent.max(child.ascent), descent.max(child.descent))
+            });
+    let mut children_render_plans = vec![];
+    for (index, (child, plan)) in terms.iter().zip(plans).enumerate() {
+        children_render_plans.push(match (child, plan) {
+            (_, Some(plan)) => plan,
+            (Element::Mo(mo), None) => {
+                mo.try_plan_with_form(text_renderer, style, form(index), Some(stretch_to))?
+            }
+            (_, None) => child.try_plan_render(text_renderer, style)?,
+        });
+    }
+    let ascent = children_render_plans
+        .iter()
+        .map(|child| child.ascent)
+        .fold(0.0, f32::max);
+    let descent = children_render_plans
+        .iter()
+        .map(|child| child.descent)
+        .fold(0.0, f32::max);
+    let width = children_render_plans
+        .iter()
+        .map(|child| child.width)
+        .sum::<f32>();
+
+    let callback = move |canvas: &mut dyn MathCanvas, offset_x: f32, offset_y: f32| {
+        let mut offset_in_row = 0.0;
+        children_render_plans.iter().for_each(|child| {
+            let y = ascent - child.ascent;
+            let child_callback = &child.callback;
+            child_callback(canvas, offset_in_row + offset_x, y + offset_y);
+            offset_in_row += child.width;
+        });
+    };
+
+    Ok(RenderingPlan {
+        callback: Box::new(callback),
+        ascent,
+        descent,
+        width,
+    })
+}
 
 impl Render for Mrow {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let paint = PixmapPaint::default();
-        let transform = Transform::default();
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        plan_row(&self.terms, text_renderer, style)
+    }
+}
 
-        let children_render_plans = self
-            .terms
-            .iter()
-            .map(|child| child.plan_render(text_renderer, font_size))
-            .collect::<Vec<_>>();
-        let baseline = children_render_plans
-            .iter()
-            .map(|child| child.baseline)
-            .max()
-            .unwrap();
-        let below_baseline = children_render_plans
-            .iter()
-            .map(|child| child.height as i32 - baseline as i32)
-            .max()
-            .unwrap() as u32;
-        let width = children_render_plans
-            .iter()
-            .map(|child| child.width)
-            .sum::<u32>();
-        let height = baseline + below_baseline;
-
-        let callback = move |text_renderer: &mut TextRenderer,
-                             pixmap: &mut Pixmap,
-                             offset_x: u32,
-                             offset_y: u32| {
-            let mut offset_in_row = 0;
-            children_render_plans.iter().for_each(|child| {
-                let y = baseline - child.baseline;
-                let child_callback = &child.callback;
-                child_callback(
-                    text_renderer,
-                    pixmap,
-                    offset_in_row + offset_x,
-                    y + offset_y,
-                );
-                offset_in_row += child.width;
-            });
-        };
+impl Render for Mphantom {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        // Laid out as a row, but nothing is drawn
+        let RenderingPlan {
+            ascent,
+            descent,
+            width,
+            ..
+        } = plan_row(&self.terms, text_renderer, style)?;
+        Ok(RenderingPlan {
+            callback: Box::new(|_: &mut dyn MathCanvas, _: f32, _: f32| {}),
+            ascent,
+            descent,
+            width,
+        })
+    }
+}
 
-        RenderingPlan {
-            callback: Box::new(callback),
-            baseline,
+impl Render for Mspace {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let x_height = text_renderer.x_height() * font_size;
+        let length =
+            |length: Option<Length>| length.map_or(0.0, |length| length.to_px(font_size, x_height));
+        // A negative width moves the next elements back, e.g. to bring symbols closer
+        Ok(RenderingPlan {
+            callback: Box::new(|_: &mut dyn MathCanvas, _: f32, _: f32| {}),
+            ascent: length(self.height).max(0.0),
+            descent: length(self.depth).max(0.0),
+            width: length(self.width),
+        })
+    }
+}
+
+impl Render for Mpadded {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let x_height = text_renderer.x_height() * font_size;
+        let RenderingPlan {
+            callback,
+            ascent,
+            descent,
             width,
-            height,
-        }
+        } = self.content.try_plan_render(text_renderer, style)?;
+
+        // New value of a dimension, which is 0 for the shifts of the content
+        let resolve = |attribute: Option<PaddedLength>, dimension: f32| {
+            let Some(PaddedLength { value, relative }) = attribute else {
+                return dimension;
+            };
+            let value = match value {
+                // A percentage already gives the new dimension from the current one, so a sign
+                // only tells whether it grows or shrinks: "+150%" and "-50%" are 1.5 and 0.5 times
+                // the dimension
+                PaddedValue::Length(Length::Percent(percentage)) => {
+                    return percentage.abs() / 100.0 * dimension;
+                }
+                PaddedValue::Length(length) => length.to_px(font_size, x_height),
+                PaddedValue::Dimension(ratio, PseudoUnit::Width) => ratio * width,
+                PaddedValue::Dimension(ratio, PseudoUnit::Height) => ratio * ascent,
+                PaddedValue::Dimension(ratio, PseudoUnit::Depth) => ratio * descent,
+            };
+            if relative { dimension + value } else { value }
+        };
+        // The dimensions can't be negative, the content can be moved either way
+        let padded_width = resolve(self.width, width).max(0.0);
+        let padded_ascent = resolve(self.height, ascent).max(0.0);
+        let padded_descent = resolve(self.depth, descent).max(0.0);
+        let lspace = resolve(self.lspace, 0.0);
+        let voffset = resolve(self.voffset, 0.0);
+
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            callback(canvas, x + lspace, y + padded_ascent - voffset - ascent);
+        };
+        Ok(RenderingPlan {
+            callback: Box::new(callback),
+            ascent: padded_ascent,
+            descent: padded_descent,
+            width: padded_width,
+        })
     }
 }
 
-impl Render for Mphantom {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let children_render_plans = self
-            .terms
-            .iter()
-            .map(|child| child.plan_render(text_renderer, font_size))
-            .collect::<Vec<_>>();
-        let baseline = children_render_plans
-            .iter()
-            .map(|child| child.baseline)
-            .max()
-            .unwrap();
-        let below_baseline = children_render_plans
-            .iter()
-            .map(|child| child.height as i32 - baseline as i32)
-            .max()
-            .unwrap() as u32;
-        let width = children_render_plans
-            .iter()
-            .map(|child| child.width)
-            .sum::<u32>();
-        let height = baseline + below_baseline;
+impl Mstyle {
+    /// Style of the content: the inherited style, changed by the attributes.
+    ///
+    /// A change of script level scales the font size, unless `mathsize` sets it explicitly.
+    pub fn style(&self, inherited: &StyleContext, text_renderer: &TextRenderer) -> StyleContext {
+        let x_height = text_renderer.x_height() * inherited.font_size;
+        let scriptminsize = self.scriptminsize.map_or(inherited.scriptminsize, |size| {
+            size.to_px(inherited.font_size, x_height)
+        });
+        let scriptlevel = match self.scriptlevel {
+            Some(ScriptLevel::Relative(increment)) => inherited.scriptlevel + increment,
+            Some(ScriptLevel::Absolute(level)) => level,
+            None => inherited.scriptlevel,
+        };
+        let scaled = StyleContext {
+            scriptminsize,
+            ..*inherited
+        }
+        .with_scriptlevel(scriptlevel, text_renderer.math_constants());
+        StyleContext {
+            font_size: self.mathsize.map_or(scaled.font_size, |size| {
+                size.to_px(inherited.font_size, x_height)
+            }),
+            displaystyle: self.displaystyle.unwrap_or(inherited.displaystyle),
+            color: self.mathcolor.unwrap_or(inherited.color),
+            background: self.mathbackground,
+            mathvariant: self.mathvariant.or(inherited.mathvariant),
+            ..scaled
+        }
+    }
+}
 
-        let callback = move |text_renderer: &mut TextRenderer,
-                             pixmap: &mut Pixmap,
-                             offset_x: u32,
-                             offset_y: u32| {};
+impl Render for Mstyle {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let style = self.style(style, text_renderer);
+        let content = self
+            .content
+            .try_plan_render(text_renderer, &style.child())?;
+        let Some(background) = style.background else {
+            return Ok(content);
+        };
 
-        RenderingPlan {
+        let RenderingPlan {
+            callback,
+            ascent,
+            descent,
+            width,
+        } = content;
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            canvas.fill_rect(x, y, width, ascent + descent, background);
+            callback(canvas, x, y);
+        };
+        Ok(RenderingPlan {
             callback: Box::new(callback),
-            baseline,
+            ascent,
+            descent,
             width,
-            height,
-        }
+        })
+    }
+}
+
+impl Render for Math {
+    /// The content is in display style when `display="block"`, unless overridden by
+    /// `displaystyle`
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let block = self.attributes.get("display").map(|display| display.trim()) == Some("block");
+        let displaystyle = match self
+            .attributes
+            .get("displaystyle")
+            .map(|value| value.trim())
+        {
+            Some("true") => true,
+            Some("false") => false,
+            _ => block,
+        };
+        let style = StyleContext {
+            displaystyle,
+            ..*style
+        };
+        self.content.try_plan_render(text_renderer, &style)
     }
 }
 
 impl Render for Mfrac {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let paint = PixmapPaint::default();
-        let transform = Transform::default();
-        let line_width = (font_size / 25.0).ceil() as u32;
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let constants = text_renderer.math_constants();
+        let default_thickness = constants.fraction_rule_thickness * font_size;
+        let line_width = match self.linethickness {
+            None => default_thickness,
+            Some(LineThickness::Relative(ratio)) => ratio * default_thickness,
+            Some(LineThickness::Length(length)) => {
+                length.to_px(font_size, text_renderer.x_height() * font_size)
+            }
+        }
+        .max(0.0);
+        let axis_height = constants.axis_height * font_size;
+        let horizontal_gap = constants.skewed_fraction_horizontal_gap * font_size;
+        let vertical_gap = constants.skewed_fraction_vertical_gap * font_size;
+        // Without a bar, the terms are stacked with a single gap between them
+        let (numer_shift, denom_shift, numer_gap, denom_gap) =
+            match (line_width > 0.0, style.displaystyle) {
+                (true, false) => (
+                    constants.fraction_numerator_shift_up,
+                    constants.fraction_denominator_shift_down,
+                    constants.fraction_numerator_gap_min,
+                    constants.fraction_denominator_gap_min,
+                ),
+                (true, true) => (
+                    constants.fraction_numerator_display_style_shift_up,
+                    constants.fraction_denominator_display_style_shift_down,
+                    constants.fraction_num_display_style_gap_min,
+                    constants.fraction_denom_display_style_gap_min,
+                ),
+                (false, false) => (
+                    constants.stack_top_shift_up,
+                    constants.stack_bottom_shift_down,
+                    constants.stack_gap_min,
+                    0.0,
+                ),
+                (false, true) => (
+                    constants.stack_top_display_style_shift_up,
+                    constants.stack_bottom_display_style_shift_down,
+                    constants.stack_display_style_gap_min,
+                    0.0,
+                ),
+            };
+        let (numer_shift, denom_shift) = (numer_shift * font_size, denom_shift * font_size);
+        let (numer_gap, denom_gap) = (numer_gap * font_size, denom_gap * font_size);
+        // The numerator and denominator are never in display style, and are one script level
+        // deeper when the fraction itself is inline
+        let part_style = if style.displaystyle {
+            StyleContext {
+                displaystyle: false,
+                ..style.child()
+            }
+        } else {
+            style
+                .child()
+                .with_scriptlevel(style.scriptlevel + 1, constants)
+        };
+        let numerator = self.numerator.try_plan_render(text_renderer, &part_style)?;
+        let denominator = self
+            .denominator
+            .try_plan_render(text_renderer, &part_style)?;
+        let color = style.color;
+
+        if self.bevelled {
+            // The numerator is raised and the denominator lowered on each side of the axis, and
+            // the slash goes through the gap between them
+            let numer_shift = axis_height + vertical_gap / 2.0 + numerator.descent;
+            let denom_shift = denominator.ascent - (axis_height - vertical_gap / 2.0);
+            let ascent = (numer_shift + numerator.ascent).max(denominator.ascent - denom_shift);
+            let descent = (denom_shift + denominator.descent).max(numerator.descent - numer_shift);
+            let slash_x = numerator.width;
+            let denominator_x = slash_x + horizontal_gap;
+            let width = denominator_x + denominator.width;
+            let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+                let baseline = y + ascent;
+                numerator.draw(canvas, x, baseline - numer_shift - numerator.ascent);
+                denominator.draw(
+                    canvas,
+                    x + denominator_x,
+                    baseline + denom_shift - denominator.ascent,
+                );
+                if line_width > 0.0 {
+                    let mut path = Path::new();
+                    path.move_to(x + slash_x, baseline + descent);
+                    path.line_to(x + denominator_x, baseline - ascent);
+                    canvas.stroke_path(&path, line_width, color);
+                }
+            };
+            return Ok(RenderingPlan {
+                callback: Box::new(callback),
+                ascent,
+                descent,
+                width,
+            });
+        }
+
         let RenderingPlan {
             callback: numer_callback,
-            baseline: numer_baseline,
+            ascent: numer_ascent,
+            descent: numer_descent,
             width: numer_width,
-            height: numer_height,
-        } = self.numerator.plan_render(text_renderer, font_size);
+        } = numerator;
         let RenderingPlan {
             callback: denom_callback,
-            baseline: denom_baseline,
+            ascent: denom_ascent,
+            descent: denom_descent,
             width: denom_width,
-            height: denom_height,
-        } = self.denominator.plan_render(text_renderer, font_size);
+        } = denominator;
+
+        // distances from the baseline of the fraction to the baselines of its terms
+        let (numer_shift, denom_shift) = if line_width > 0.0 {
+            // the bar is centered on the math axis
+            let bar_top = axis_height + line_width / 2.0;
+            let bar_bottom = bar_top - line_width;
+            (
+                numer_shift.max(bar_top + numer_gap + numer_descent),
+                denom_shift.max(denom_gap + denom_ascent - bar_bottom),
+            )
+        } else {
+            // terms which are too close are moved apart evenly
+            let gap = (numer_shift - numer_descent) - (denom_ascent - denom_shift);
+            let correction = (numer_gap - gap).max(0.0) / 2.0;
+            (numer_shift + correction, denom_shift + correction)
+        };
+        let bar_top = axis_height + line_width / 2.0;
 
         let width = numer_width.max(denom_width);
-        let term_height = numer_height.max(denom_height);
-        let height = (2 * term_height + line_width);
+        let ascent = (numer_shift + numer_ascent).max(bar_top);
+        let descent = denom_shift + denom_descent;
 
-        let (numerator_x_offset, denominator_x_offset) = match numer_width.cmp(&denom_width) {
-            Ordering::Less => ((denom_width - numer_width) / 2, 0),
-            Ordering::Equal => (0, 0),
-            Ordering::Greater => (0, (numer_width - denom_width) / 2),
+        let align = |alignment: ColumnAlign, term_width: f32| match alignment {
+            ColumnAlign::Left => 0.0,
+            ColumnAlign::Center => (width - term_width) / 2.0,
+            ColumnAlign::Right => width - term_width,
         };
+        let numerator_x_offset = align(self.numalign, numer_width);
+        let denominator_x_offset = align(self.denomalign, denom_width);
 
-        let baseline = term_height + line_width / 2;
-
-        let callback = move |text_renderer: &mut TextRenderer,
-                             pixmap: &mut Pixmap,
-                             x_offset: u32,
-                             y_offset: u32| {
+        let callback = move |canvas: &mut dyn MathCanvas, x_offset: f32, y_offset: f32| {
             numer_callback(
-                text_renderer,
-                pixmap,
+                canvas,
                 numerator_x_offset + x_offset,
-                (term_height - numer_height) + y_offset,
+                (ascent - numer_shift - numer_ascent) + y_offset,
             );
             denom_callback(
-                text_renderer,
-                pixmap,
+                canvas,
                 denominator_x_offset + x_offset,
-                (term_height + line_width) + y_offset,
+                (ascent + denom_shift - denom_ascent) + y_offset,
             );
-            pixmap.fill_rect(
-                IntRect::from_xywh(
-                    x_offset as i32,
-                    (y_offset + term_height) as i32,
+            if line_width > 0.0 {
+                canvas.fill_rect(
+                    x_offset,
+                    y_offset + ascent - bar_top,
                     width,
                     line_width,
-                )
-                .unwrap()
-                .to_rect(),
-                &Paint::default(),
-                transform,
-                None,
-            );
+                    color,
+                );
+            }
         };
 
-        RenderingPlan {
+        Ok(RenderingPlan {
             callback: Box::new(callback),
-            baseline,
+            ascent,
+            descent,
             width,
-            height,
-        }
+        })
     }
 }
 
@@ -333,102 +1267,364 @@ struct _Mroot<'a> {
 }
 
 impl Render for _Mroot<'_> {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
-        let paint = Paint::default();
-        let pixmappaint = PixmapPaint::default();
-        let transform = Transform::default();
-        let line_width = (font_size / 25.0).ceil() as u32;
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let constants = text_renderer.math_constants();
+        let line_width = constants.radical_rule_thickness * font_size;
+        let gap = if style.displaystyle {
+            constants.radical_display_style_vertical_gap
+        } else {
+            constants.radical_vertical_gap
+        } * font_size;
+        let extra_ascender = constants.radical_extra_ascender * font_size;
+        // The index is drawn two script levels deeper, in inline style
+        let index_style = StyleContext {
+            displaystyle: false,
+            ..style
+                .child()
+                .with_scriptlevel(style.scriptlevel + 2, constants)
+        };
+        let kern_before = constants.radical_kern_before_degree * font_size;
+        let kern_after = constants.radical_kern_after_degree * font_size;
+        let bottom_raise = constants.radical_degree_bottom_raise_percent;
 
         let RenderingPlan {
             callback: inner_callback,
-            baseline: inner_baseline,
+            ascent: inner_ascent,
+            descent: inner_descent,
             width: inner_width,
-            height: inner_height,
-        } = self.base.plan_render(text_renderer, font_size);
-
-        let baseline = inner_baseline + line_width;
-        let width = inner_width + inner_height / 2;
-        let height = inner_height + 3 * line_width;
+        } = self.base.try_plan_render(text_renderer, style)?;
+
+        // The radical sign covers the radicand, the gap and the overbar, whose top is aligned with
+        // the top of the sign
+        let sign_top = inner_ascent + gap + line_width;
+        let sign =
+            plan_stretched_operator(text_renderer, "√", style, sign_top, inner_descent, false)?;
+        let sign_width = sign.width;
+        let radical_ascent = sign_top + extra_ascender;
+        let radical_descent = inner_descent.max(sign.height() - sign_top);
+        let radical_width = sign_width + inner_width;
+        // bottom of the radical sign, which the index is raised from
+        let sign_height = sign.height();
+
+        // Radical sign, overbar and radicand, with the top left corner at (x, y)
+        let color = style.color;
+        let draw_radical = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            let sign_y = y + extra_ascender;
+            sign.draw(canvas, x, sign_y);
+            canvas.fill_rect(x + sign_width, sign_y, inner_width, line_width, color);
+            inner_callback(canvas, x + sign_width, y + radical_ascent - inner_ascent);
+        };
 
-        let callback =
-            move |text_renderer: &mut TextRenderer, pixmap: &mut Pixmap, x: u32, y: u32| {
-                inner_callback(
-                    text_renderer,
-                    pixmap,
-                    x + inner_height / 2,
-                    y + 2 * line_width,
-                );
+        let Some(index) = self.index else {
+            return Ok(RenderingPlan {
+                callback: Box::new(draw_radical),
+                ascent: radical_ascent,
+                descent: radical_descent,
+                width: radical_width,
+            });
+        };
 
-                let x = x as f32;
-                let y = y as f32;
-                let mut root_linepath = PathBuilder::new();
-                let mut stroke = Stroke::default();
-                stroke.width = line_width as f32;
-                root_linepath.move_to(x + width as f32, y + 3. * line_width as f32 / 2.);
-                root_linepath.line_to(
-                    x + inner_height as f32 / 2.,
-                    y + 3. * line_width as f32 / 2.,
-                );
-                root_linepath.line_to(
-                    x + inner_height as f32 / 4.,
-                    y + height as f32 - line_width as f32,
-                );
-                root_linepath.line_to(
-                    x + height as f32 / 9.,
-                    y + 2. * (height as f32 - line_width as f32) / 3., // + line_width?
-                );
-                root_linepath.line_to(x, y + 7. * (height as f32 - line_width as f32) / 9.); // + linewidth?
-                // root_linepath.close();
-                pixmap.stroke_path(
-                    &root_linepath.finish().unwrap(),
-                    &paint,
-                    &stroke,
-                    Transform::identity(),
-                    None,
-                );
-            };
+        // The index is placed above the left part of the radical sign
+        let index = index.try_plan_render(text_renderer, &index_style)?;
+        let sign_bottom = sign_height - sign_top;
+        let index_baseline = bottom_raise * sign_height - sign_bottom + index.descent;
+        let ascent = radical_ascent.max(index_baseline + index.ascent);
+        let descent = radical_descent.max(index.descent - index_baseline);
+        // A negative kern after the index moves the radical sign below it, but not past its start
+        let index_x = kern_before.max(0.0);
+        let radical_x = (index_x + index.width + kern_after).max(0.0);
+        let width = radical_x + radical_width;
+
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            draw_radical(canvas, x + radical_x, y + ascent - radical_ascent);
+            index.draw(
+                canvas,
+                x + index_x,
+                y + ascent - index_baseline - index.ascent,
+            );
+        };
 
-        RenderingPlan {
+        Ok(RenderingPlan {
             callback: Box::new(callback),
-            baseline,
+            ascent,
+            descent,
             width,
-            height,
-        }
+        })
     }
 }
+
 impl Render for Mroot {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
         _Mroot {
             base: &self.base,
-            index: self.index.as_ref().map(|x| &(**x)), // is there a cleaner way to do this?
+            index: self.index.as_deref(),
         }
-        .plan_render(text_renderer, font_size)
+        .try_plan_render(text_renderer, style)
     }
 }
 impl Render for Msqrt {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
         _Mroot {
             base: &self.term,
             index: None,
         }
-        .plan_render(text_renderer, font_size)
+        .try_plan_render(text_renderer, style)
+    }
+}
+
+/// Padding between the content of an `<menclose>` and its notations, in line widths
+const ENCLOSE_PADDING_RATIO: f32 = 3.0;
+/// Horizontal extent of the curve of a long division, relative to its height
+const LONGDIV_BULGE_RATIO: f32 = 0.15;
+/// Distance from the ends to the control points of the cubic curves approximating quarter circles
+const QUARTER_CIRCLE_KAPPA: f32 = 0.552_284_8;
+
+/// Append an ellipse, or a quarter of it, approximated with cubic curves. The quarters are
+/// numbered clockwise from the one going from the right to the bottom of the ellipse.
+fn ellipse_arc(path: &mut Path, cx: f32, cy: f32, rx: f32, ry: f32, quarters: std::ops::Range<u8>) {
+    let (kx, ky) = (rx * QUARTER_CIRCLE_KAPPA, ry * QUARTER_CIRCLE_KAPPA);
+    for quarter in quarters {
+        // start of the quarter and direction of the next one, clockwise with y pointing down
+        let (sx, sy, ex, ey) = match quarter % 4 {
+            0 => (1.0, 0.0, 0.0, 1.0),
+            1 => (0.0, 1.0, -1.0, 0.0),
+            2 => (-1.0, 0.0, 0.0, -1.0),
+            _ => (0.0, -1.0, 1.0, 0.0),
+        };
+        let (x0, y0) = (cx + sx * rx, cy + sy * ry);
+        let (x1, y1) = (cx + ex * rx, cy + ey * ry);
+        if path.is_empty() {
+            path.move_to(x0, y0);
+        }
+        path.curve_to(
+            x0 + ex * kx,
+            y0 + ey * ky,
+            x1 + sx * kx,
+            y1 + sy * ky,
+            x1,
+            y1,
+        );
+    }
+}
+
+impl Render for Menclose {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let constants = text_renderer.math_constants();
+        let line_width = constants.overbar_rule_thickness * font_size;
+        let padding = ENCLOSE_PADDING_RATIO * line_width;
+        let has = |notations: &[Notation]| {
+            notations
+                .iter()
+                .any(|notation| self.notation.contains(notation))
+        };
+
+        let content = if has(&[Notation::Radical]) {
+            _Mroot {
+                base: &self.content,
+                index: None,
+            }
+            .try_plan_render(text_renderer, style)?
+        } else {
+            self.content.try_plan_render(text_renderer, style)?
+        };
+
+        // Space between the content and the edges of the element, on each side
+        let side = |notations: &[Notation]| {
+            if has(notations) {
+                padding + line_width
+            } else {
+                0.0
+            }
+        };
+        let mut left = side(&[
+            Notation::Box,
+            Notation::RoundedBox,
+            Notation::Left,
+            Notation::Longdiv,
+        ]);
+        let mut right = side(&[
+            Notation::Box,
+            Notation::RoundedBox,
+            Notation::Right,
+            Notation::Actuarial,
+            Notation::Madruwb,
+        ]);
+        let mut top = side(&[
+            Notation::Box,
+            Notation::RoundedBox,
+            Notation::Top,
+            Notation::Actuarial,
+            Notation::Longdiv,
+        ]);
+        let mut bottom = side(&[
+            Notation::Box,
+            Notation::RoundedBox,
+            Notation::Bottom,
+            Notation::Madruwb,
+        ]);
+        let padded_height = content.height() + 2.0 * padding;
+        let longdiv_bulge = LONGDIV_BULGE_RATIO * padded_height;
+        if has(&[Notation::Longdiv]) {
+            left += longdiv_bulge;
+        }
+        // The circle goes through the corners of the padded content
+        let (circle_rx, circle_ry) = (
+            (content.width + 2.0 * padding) / std::f32::consts::SQRT_2,
+            padded_height / std::f32::consts::SQRT_2,
+        );
+        if has(&[Notation::Circle]) {
+            let margin_x = circle_rx - content.width / 2.0 + line_width / 2.0;
+            let margin_y = circle_ry - content.height() / 2.0 + line_width / 2.0;
+            left = left.max(margin_x);
+            right = right.max(margin_x);
+            top = top.max(margin_y);
+            bottom = bottom.max(margin_y);
+        }
+
+        let width = left + content.width + right;
+        let ascent = top + content.ascent;
+        let descent = content.descent + bottom;
+        let height = ascent + descent;
+
+        // Lines are centered on these coordinates, so that they stay within the element
+        let (x0, x1) = (line_width / 2.0, width - line_width / 2.0);
+        let (y0, y1) = (line_width / 2.0, height - line_width / 2.0);
+        let line = |from: (f32, f32), to: (f32, f32)| {
+            let mut path = Path::new();
+            path.move_to(from.0, from.1);
+            path.line_to(to.0, to.1);
+            path
+        };
+        let mut paths = vec![];
+        for notation in &self.notation {
+            match notation {
+                Notation::Box => {
+                    let mut path = line((x0, y0), (x1, y0));
+                    path.line_to(x1, y1);
+                    path.line_to(x0, y1);
+                    path.close();
+                    paths.push(path);
+                }
+                Notation::RoundedBox => {
+                    let radius = padding.min((x1 - x0) / 2.0).min((y1 - y0) / 2.0);
+                    let mut path = Path::new();
+                    ellipse_arc(&mut path, x1 - radius, y1 - radius, radius, radius, 0..1);
+                    path.line_to(x0 + radius, y1);
+                    ellipse_arc(&mut path, x0 + radius, y1 - radius, radius, radius, 1..2);
+                    path.line_to(x0, y0 + radius);
+                    ellipse_arc(&mut path, x0 + radius, y0 + radius, radius, radius, 2..3);
+                    path.line_to(x1 - radius, y0);
+                    ellipse_arc(&mut path, x1 - radius, y0 + radius, radius, radius, 3..4);
+                    path.close();
+                    paths.push(path);
+                }
+                Notation::Circle => {
+                    let mut path = Path::new();
+                    let center = (left + content.width / 2.0, top + content.height() / 2.0);
+                    ellipse_arc(&mut path, center.0, center.1, circle_rx, circle_ry, 0..4);
+                    path.close();
+                    paths.push(path);
+                }
+                Notation::Left => paths.push(line((x0, y0), (x0, y1))),
+                Notation::Right => paths.push(line((x1, y0), (x1, y1))),
+                Notation::Top => paths.push(line((x0, y0), (x1, y0))),
+                Notation::Bottom => paths.push(line((x0, y1), (x1, y1))),
+                Notation::UpDiagonalStrike => paths.push(line((x0, y1), (x1, y0))),
+                Notation::DownDiagonalStrike => paths.push(line((x0, y0), (x1, y1))),
+                // Strikes go through the middle of the content, which the padding can move
+                Notation::HorizontalStrike => {
+                    let middle = top + content.height() / 2.0;
+                    paths.push(line((x0, middle), (x1, middle)));
+                }
+                Notation::VerticalStrike => {
+                    let middle = left + content.width / 2.0;
+                    paths.push(line((middle, y0), (middle, y1)));
+                }
+                Notation::Actuarial => {
+                    let mut path = line((x0, y0), (x1, y0));
+                    path.line_to(x1, y1);
+                    paths.push(path);
+                }
+                Notation::Madruwb => {
+                    let mut path = line((x1, y0), (x1, y1));
+                    path.line_to(x0, y1);
+                    paths.push(path);
+                }
+                Notation::Longdiv => {
+                    // Bar over the dividend, and a curve bulging to the right on its left
+                    let mut path = line((x1, y0), (x0, y0));
+                    path.quad_to(x0 + 2.0 * longdiv_bulge, height / 2.0, x0, y1);
+                    paths.push(path);
+                }
+                Notation::Radical => {}
+            }
+        }
+
+        let color = style.color;
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            content.draw(canvas, x + left, y + top);
+            for path in &paths {
+                canvas.stroke_path(&path.transformed(1.0, 1.0, x, y), line_width, color);
+            }
+        };
+
+        Ok(RenderingPlan {
+            callback: Box::new(callback),
+            ascent,
+            descent,
+            width,
+        })
     }
 }
 
 impl Render for Element {
-    fn plan_render(&self, text_renderer: &mut TextRenderer, font_size: f32) -> RenderingPlan {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
         match self {
-            Element::Mi(mi) => mi.plan_render(text_renderer, font_size),
-            Element::Mn(mn) => mn.plan_render(text_renderer, font_size),
-            Element::Mo(mo) => mo.plan_render(text_renderer, font_size),
-            Element::Mtext(mtext) => mtext.plan_render(text_renderer, font_size),
-            Element::Msup(msup) => msup.plan_render(text_renderer, font_size),
-            Element::Msub(msub) => msub.plan_render(text_renderer, font_size),
-            Element::Mfrac(mfrac) => mfrac.plan_render(text_renderer, font_size),
-            Element::Mroot(mroot) => mroot.plan_render(text_renderer, font_size),
-            Element::Msqrt(msqrt) => msqrt.plan_render(text_renderer, font_size),
-            Element::Mrow(mrow) => mrow.plan_render(text_renderer, font_size),
-            Element::Mphantom(mphantom) => mphantom.plan_render(text_renderer, font_size),
+            Element::Mi(mi) => mi.try_plan_render(text_renderer, style),
+            Element::Mn(mn) => mn.try_plan_render(text_renderer, style),
+            Element::Mo(mo) => mo.try_plan_render(text_renderer, style),
+            Element::Mtext(mtext) => mtext.try_plan_render(text_renderer, style),
+            Element::Msup(msup) => msup.try_plan_render(text_renderer, style),
+            Element::Msub(msub) => msub.try_plan_render(text_renderer, style),
+            Element::Msubsup(msubsup) => msubsup.try_plan_render(text_renderer, style),
+            Element::Munder(munder) => munder.try_plan_render(text_renderer, style),
+            Element::Mover(mover) => mover.try_plan_render(text_renderer, style),
+            Element::Munderover(munderover) => munderover.try_plan_render(text_renderer, style),
+            Element::Mmultiscripts(mmultiscripts) => {
+                mmultiscripts.try_plan_render(text_renderer, style)
+            }
+            Element::Mstyle(mstyle) => mstyle.try_plan_render(text_renderer, style),
+            Element::Menclose(menclose) => menclose.try_plan_render(text_renderer, style),
+            Element::Mtable(mtable) => mtable.try_plan_render(text_renderer, style),
+            Element::Mfrac(mfrac) => mfrac.try_plan_render(text_renderer, style),
+            Element::Mroot(mroot) => mroot.try_plan_render(text_renderer, style),
+            Element::Msqrt(msqrt) => msqrt.try_plan_render(text_renderer, style),
+            Element::Mrow(mrow) => mrow.try_plan_render(text_renderer, style),
+            Element::Mphantom(mphantom) => mphantom.try_plan_render(text_renderer, style),
+            Element::Mspace(mspace) => mspace.try_plan_render(text_renderer, style),
+            Element::Mpadded(mpadded) => mpadded.try_plan_render(text_renderer, style),
         }
     }
 }
@@ -436,11 +1632,14 @@ impl Render for Element {
 #[cfg(test)]
 mod tests {
     extern crate test;
-    use crate::mml_types::mphantom;
-
     use super::*;
+    use crate::canvas::{Color, PathCommand};
+    use crate::mml_types::{
+        menclose, mfrac, mi, mmultiscripts, mn, mo, mpadded, mphantom, mroot, mrow, mstyle, msub,
+        msubsup, msup, mtext, munder, munderover, stretchy_mo,
+    };
+    use crate::test_fonts::math_font_renderer;
     use function_name::named;
-    use parley::swash::text;
     use test::Bencher;
 
     #[named]
@@ -449,7 +1648,7 @@ mod tests {
         let whole = mi("β");
         let font_size = 100.0;
 
-        let img = whole.render(&mut TextRenderer::new(), font_size);
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -461,7 +1660,7 @@ mod tests {
         let whole = msup(mi("β"), mn("2"));
         let font_size = 100.0;
 
-        let img = whole.render(&mut TextRenderer::new(), font_size);
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -473,7 +1672,7 @@ mod tests {
         let whole = mrow(vec![mi("β"), mphantom(vec![mo("+")]), mn("2")]);
         let font_size = 100.0;
 
-        let img = whole.render(&mut TextRenderer::new(), font_size);
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -485,7 +1684,7 @@ mod tests {
         let whole = msup(msub(mi("β"), mi("α")), mn("2"));
         let font_size = 100.0;
 
-        let img = whole.render(&mut TextRenderer::new(), font_size);
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -497,19 +1696,729 @@ mod tests {
         let fraction = mfrac(mi("α"), mi("β"));
         let font_size = 100.0;
 
-        let img = fraction.render(&mut TextRenderer::new(), font_size);
+        let img = fraction.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[named]
+    #[test]
+    fn beta_subsup() {
+        let whole = msubsup(mi("β"), mi("α"), mn("2"));
+        let font_size = 100.0;
+
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn subsup_scripts_are_stacked() {
+        let mut text_renderer = TextRenderer::new();
+        let font_size = 100.0;
+        let gap_min = text_renderer.math_constants().sub_superscript_gap_min * font_size;
+        let script_ratio = text_renderer.math_constants().script_percent_scale_down;
+
+        let base = mi("β").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let nested = msup(msub(mi("β"), mi("α")), mn("2"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let stacked = msubsup(mi("β"), mi("α"), mn("2"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!(stacked.width < nested.width);
+
+        // Tall scripts are moved apart to keep the minimal gap
+        let subscript = mfrac(mn("1"), mn("2")).plan_render(
+            &mut text_renderer,
+            &StyleContext::new(font_size * script_ratio),
+        );
+        let stacked = msubsup(mi("β"), mfrac(mn("1"), mn("2")), mfrac(mn("1"), mn("2")))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!(stacked.height() >= 2.0 * subscript.height() + gap_min - 0.01);
+        assert!(stacked.ascent > base.ascent && stacked.descent > base.descent);
+    }
+
+    #[named]
+    #[test]
+    fn sum_limits() {
+        let sum = Element::Mo(Mo {
+            operator: "∑".into(),
+            movablelimits: Some(false),
+            ..Default::default()
+        });
+        let hat = Element::Mover(Mover {
+            base: mi("x").into(),
+            overscript: mo("ˆ").into(),
+            accent: Some(true),
+        });
+        let whole = mrow(vec![
+            munderover(sum, mrow(vec![mi("i"), mo("="), mn("1")]), mi("n")),
+            hat,
+        ]);
+        let font_size = 100.0;
+
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn movable_limits() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        let upper_gap_min = text_renderer.math_constants().upper_limit_gap_min * font_size;
+        let lower_gap_min = text_renderer.math_constants().lower_limit_gap_min * font_size;
+        let script_ratio = text_renderer.math_constants().script_percent_scale_down;
+        let lower_limit = || mrow(vec![mi("i"), mo("="), mn("1")]);
+
+        // Limits of a sum are placed as scripts in inline style
+        let scripts = msubsup(mo("∑"), lower_limit(), mi("n"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let limits = munderover(mo("∑"), lower_limit(), mi("n"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert_eq!(limits.width, scripts.width);
+        assert_eq!(limits.height(), scripts.height());
+        let scripts =
+            msub(mo("lim"), mi("n")).plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let limits = munder(mo("lim"), mi("n"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert_eq!(limits.width, scripts.width);
+
+        let sum = || {
+            Element::Mo(Mo {
+                operator: "∑".into(),
+                movablelimits: Some(false),
+                ..Default::default()
+            })
+        };
+        let base = sum().plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let underscript = lower_limit().plan_render(
+            &mut text_renderer,
+            &StyleContext::new(font_size * script_ratio),
+        );
+        let overscript = mi("n").plan_render(
+            &mut text_renderer,
+            &StyleContext::new(font_size * script_ratio),
+        );
+        let limits = munderover(sum(), lower_limit(), mi("n"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert_eq!(limits.width, base.width.max(underscript.width));
+        assert!(limits.ascent >= base.ascent + upper_gap_min + overscript.height() - 0.01);
+        assert!(limits.descent >= base.descent + lower_gap_min + underscript.height() - 0.01);
+
+        // Accents are drawn at the size of the base, raised above tall bases
+        let accent_base_height = text_renderer.math_constants().accent_base_height * font_size;
+        let tall_base = mi("M").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let accent = mo("ˆ").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let hat = Element::Mover(Mover {
+            base: mi("M").into(),
+            overscript: mo("ˆ").into(),
+            accent: Some(true),
+        })
+        .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let expected_ascent = tall_base.ascent - accent_base_height + accent.ascent;
+        assert!((hat.ascent - expected_ascent).abs() < 0.01);
+    }
+
+    #[named]
+    #[test]
+    fn carbon_isotope() {
+        let whole = mrow(vec![
+            mmultiscripts(mi("C"), vec![], vec![(Some(mn("6")), Some(mn("14")))]),
+            mo(","),
+            mmultiscripts(
+                mi("R"),
+                vec![(None, Some(mi("i"))), (Some(mi("jkl")), None)],
+                vec![],
+            ),
+        ]);
+        let font_size = 100.0;
+
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn italic_correction() {
+        let mut text_renderer = math_font_renderer();
+        let style = StyleContext::new(100.0);
+        let constants = text_renderer.math_constants();
+        let space_after_script = constants.space_after_script * style.font_size;
+        let script_style = style.script(constants);
+        let glyph = text_renderer.math_glyph_id('∫').unwrap();
+        let italic_correction =
+            text_renderer.math_glyph_info().italics_correction(glyph) * style.font_size;
+        assert!(italic_correction > 0.0);
+
+        // The superscript of an integral sits past its overhang, the subscript tucks under it
+        let base = mo("∫").plan_render(&mut text_renderer, &style);
+        let script = mn("1").plan_render(&mut text_renderer, &script_style);
+        let unkerned_width = base.width + script.width + space_after_script;
+        let superscript = msup(mo("∫"), mn("1")).plan_render(&mut text_renderer, &style);
+        let subscript = msub(mo("∫"), mn("1")).plan_render(&mut text_renderer, &style);
+        assert!((superscript.width - (unkerned_width + italic_correction)).abs() < 0.01);
+        assert!((subscript.width - (unkerned_width - italic_correction)).abs() < 0.01);
+    }
+
+    #[test]
+    fn multiscripts_share_baselines() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        let space_after_script = text_renderer.math_constants().space_after_script * font_size;
+        let script_ratio = text_renderer.math_constants().script_percent_scale_down;
+
+        // Scripts in different pairs are shifted as if they were in the same pair
+        let tensor = mmultiscripts(
+            mi("R"),
+            vec![(None, Some(mi("i"))), (Some(mi("jkl")), None)],
+            vec![],
+        )
+        .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let subsup = msubsup(mi("R"), mi("jkl"), mi("i"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!((tensor.ascent - subsup.ascent).abs() < 0.01);
+        assert!((tensor.descent - subsup.descent).abs() < 0.01);
+        assert!(tensor.width > subsup.width);
+
+        // Prescripts are before the base
+        let base = mi("C").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let mass = mn("14").plan_render(
+            &mut text_renderer,
+            &StyleContext::new(font_size * script_ratio),
+        );
+        let isotope = mmultiscripts(mi("C"), vec![], vec![(Some(mn("6")), Some(mn("14")))])
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let expected_width = space_after_script + mass.width + base.width;
+        assert!((isotope.width - expected_width).abs() < 0.01);
+    }
+
+    #[named]
+    #[test]
+    fn fraction_variants() {
+        let fraction = |numerator, denominator| match mfrac(numerator, denominator) {
+            Element::Mfrac(mfrac) => mfrac,
+            _ => unreachable!(),
+        };
+        let binomial = || {
+            let mut binomial = fraction(mi("n"), mi("k"));
+            binomial.linethickness = Some(LineThickness::Relative(0.0));
+            Element::Mfrac(binomial)
+        };
+        let bevelled = || {
+            let mut bevelled = fraction(mn("1"), mn("2"));
+            bevelled.bevelled = true;
+            Element::Mfrac(bevelled)
+        };
+        let aligned = |numalign| {
+            let mut aligned = fraction(mn("1"), mrow(vec![mi("x"), mo("+"), mn("1")]));
+            aligned.numalign = numalign;
+            aligned.linethickness = Some(LineThickness::Relative(2.0));
+            Element::Mfrac(aligned)
+        };
+        let font_size = 100.0;
+        let mut text_renderer = TextRenderer::new();
+        let style = StyleContext::new(font_size);
+
+        // Without its line, the fraction keeps the width of its operands
+        let binomial_plan = binomial().plan_render(&mut text_renderer, &style);
+        let plain = mfrac(mi("n"), mi("k")).plan_render(&mut text_renderer, &style);
+        assert_eq!(binomial_plan.width, plain.width);
+        // The bevelled fraction puts its operands side by side
+        let bevelled_plan = bevelled().plan_render(&mut text_renderer, &style);
+        let stacked = mfrac(mn("1"), mn("2")).plan_render(&mut text_renderer, &style);
+        assert!(bevelled_plan.width > stacked.width);
+        assert!(bevelled_plan.height() < stacked.height());
+        // The alignment moves the numerator without changing the size of the fraction
+        let aligned_plan = aligned(ColumnAlign::Left).plan_render(&mut text_renderer, &style);
+        let centered = aligned(ColumnAlign::Center).plan_render(&mut text_renderer, &style);
+        assert_eq!(aligned_plan.width, centered.width);
+        assert_eq!(aligned_plan.ascent, centered.ascent);
+        assert_eq!(aligned_plan.descent, centered.descent);
+
+        let whole = mrow(vec![
+            stretchy_mo("("),
+            binomial(),
+            stretchy_mo(")"),
+            bevelled(),
+            aligned(ColumnAlign::Left),
+        ]);
+        let plan = whole.plan_render(&mut text_renderer, &style);
+        assert!(plan.width > binomial_plan.width + bevelled_plan.width + aligned_plan.width);
+
+        let img = whole.render(&mut text_renderer, &style);
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn fraction_attributes() {
+        let mut text_renderer = math_font_renderer();
+        let style = StyleContext::new(100.0);
+        let fraction = || match mfrac(mn("1"), mn("2")) {
+            Element::Mfrac(mfrac) => mfrac,
+            _ => unreachable!(),
+        };
+        let svg = |fraction: Mfrac, text_renderer: &mut TextRenderer| {
+            Element::Mfrac(fraction).render_svg(text_renderer, &style)
+        };
+        assert_eq!(
+            svg(fraction(), &mut text_renderer).matches("<rect").count(),
+            1
+        );
+
+        // No bar is drawn with a zero thickness
+        let mut stack = fraction();
+        stack.linethickness = Some(LineThickness::Length(Length::Px(0.0)));
+        assert_eq!(svg(stack, &mut text_renderer).matches("<rect").count(), 0);
+
+        // Bevelled fractions are side by side, around a slash
+        let constants = text_renderer.math_constants();
+        let horizontal_gap = constants.skewed_fraction_horizontal_gap * style.font_size;
+        let part_style = style.child().with_scriptlevel(1, constants);
+        let part = mn("1").plan_render(&mut text_renderer, &part_style);
+        let mut bevelled = fraction();
+        bevelled.bevelled = true;
+        let plan = Element::Mfrac(bevelled).plan_render(&mut text_renderer, &style);
+        let expected_width = 2.0 * part.width + horizontal_gap;
+        assert!((plan.width - expected_width).abs() < 0.5);
+        let mut bevelled = fraction();
+        bevelled.bevelled = true;
+        let svg = svg(bevelled, &mut text_renderer);
+        assert_eq!(svg.matches("stroke-width").count(), 1);
+        assert_eq!(svg.matches("<rect").count(), 0);
+
+        // Display style fractions use larger shifts and full size terms
+        let inline = mfrac(mn("1"), mn("2")).plan_render(&mut text_renderer, &style);
+        let display = StyleContext {
+            displaystyle: true,
+            ..style
+        };
+        let display = mfrac(mn("1"), mn("2")).plan_render(&mut text_renderer, &display);
+        assert!(display.ascent > inline.ascent);
+        assert!(display.descent > inline.descent);
+    }
+
+    #[named]
+    #[test]
+    fn nested_superscripts() {
+        let superscripts = || msup(mi("x"), msup(mi("y"), msup(mi("z"), mi("w"))));
+        let fractions = || mfrac(mn("1"), mfrac(mn("1"), mi("n")));
+        let whole = mrow(vec![superscripts(), mo("+"), fractions()]);
+        let font_size = 40.0;
+        let mut text_renderer = TextRenderer::new();
+        let style = StyleContext::new(font_size);
+
+        // Each script level is smaller than the previous one
+        let superscripts_plan = superscripts().plan_render(&mut text_renderer, &style);
+        let unscaled =
+            mrow(vec![mi("x"), mi("y"), mi("z"), mi("w")]).plan_render(&mut text_renderer, &style);
+        let base = mi("x").plan_render(&mut text_renderer, &style);
+        assert!(superscripts_plan.width > base.width);
+        assert!(superscripts_plan.width < unscaled.width);
+        assert!(superscripts_plan.ascent > base.ascent);
+        let fractions_plan = fractions().plan_render(&mut text_renderer, &style);
+        let flat = mfrac(mn("1"), mn("1")).plan_render(&mut text_renderer, &style);
+        assert!(fractions_plan.height() > flat.height());
+        assert!(fractions_plan.height() < 2.0 * flat.height());
+
+        let plan = whole.plan_render(&mut text_renderer, &style);
+        assert!(plan.width > superscripts_plan.width + fractions_plan.width);
+        assert_eq!(
+            plan.ascent,
+            superscripts_plan.ascent.max(fractions_plan.ascent)
+        );
+        assert_eq!(
+            plan.descent,
+            superscripts_plan.descent.max(fractions_plan.descent)
+        );
+
+        let img = whole.render(&mut text_renderer, &style);
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[named]
+    #[test]
+    fn styled() {
+        let highlighted = || {
+            let Element::Mstyle(mut highlighted) = mstyle(mfrac(mi("a"), mi("b"))) else {
+                unreachable!()
+            };
+            highlighted.mathcolor = Some(Color::from_rgba8(200, 0, 0, 255));
+            highlighted.mathbackground = Some(Color::from_rgba8(255, 240, 150, 255));
+            Element::Mstyle(highlighted)
+        };
+        let large = || {
+            let Element::Mstyle(mut large) = mstyle(msup(mi("x"), mn("2"))) else {
+                unreachable!()
+            };
+            large.mathsize = Some(Length::Em(1.5));
+            Element::Mstyle(large)
+        };
+        let font_size = 100.0;
+        let mut text_renderer = TextRenderer::new();
+        let style = StyleContext::new(font_size);
+
+        // Colors leave the layout unchanged, while the size scales it
+        let fraction = mfrac(mi("a"), mi("b")).plan_render(&mut text_renderer, &style);
+        let highlighted_plan = highlighted().plan_render(&mut text_renderer, &style);
+        assert_eq!(highlighted_plan.width, fraction.width);
+        assert_eq!(highlighted_plan.ascent, fraction.ascent);
+        assert_eq!(highlighted_plan.descent, fraction.descent);
+        let square = msup(mi("x"), mn("2")).plan_render(&mut text_renderer, &style);
+        let large_plan = large().plan_render(&mut text_renderer, &style);
+        assert!((large_plan.width - 1.5 * square.width).abs() < 0.5);
+        assert!((large_plan.ascent - 1.5 * square.ascent).abs() < 0.5);
+        assert!((large_plan.descent - 1.5 * square.descent).abs() < 0.5);
+
+        let whole = mrow(vec![highlighted(), mo("+"), large()]);
+        let plan = whole.plan_render(&mut text_renderer, &style);
+        assert!(plan.width > highlighted_plan.width + large_plan.width);
+        assert_eq!(plan.ascent, highlighted_plan.ascent.max(large_plan.ascent));
+        assert_eq!(
+            plan.descent,
+            highlighted_plan.descent.max(large_plan.descent)
+        );
+
+        let img = whole.render(&mut text_renderer, &style);
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn style_inheritance() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        let style = StyleContext::new(font_size);
+
+        let plain = mi("x").plan_render(&mut text_renderer, &style);
+        let Element::Mstyle(mut large) = mstyle(mi("x")) else {
+            unreachable!()
+        };
+        large.mathsize = Some(Length::Em(2.0));
+        let large = Element::Mstyle(large).plan_render(&mut text_renderer, &style);
+        assert!((large.width - 2.0 * plain.width).abs() < 0.5);
+
+        // Limits of a sum stay under and over it in display style
+        let sum = || munderover(mo("∑"), mi("i"), mi("n"));
+        let inline = sum().plan_render(&mut text_renderer, &style);
+        let Element::Mstyle(mut display) = mstyle(sum()) else {
+            unreachable!()
+        };
+        display.displaystyle = Some(true);
+        let display = Element::Mstyle(display).plan_render(&mut text_renderer, &style);
+        assert!(display.width < inline.width);
+        assert!(display.height() > inline.height());
+
+        // The sum itself is drawn with a larger variant, centered on the axis
+        let display_style = StyleContext {
+            displaystyle: true,
+            ..style
+        };
+        let constants = text_renderer.math_constants();
+        let min_height = constants.display_operator_min_height * font_size;
+        let axis_height = constants.axis_height * font_size;
+        let inline_sum = mo("∑").plan_render(&mut text_renderer, &style);
+        let display_sum = mo("∑").plan_render(&mut text_renderer, &display_style);
+        assert!(display_sum.height() > inline_sum.height());
+        assert!(display_sum.height() >= min_height);
+        assert!(((display_sum.ascent - display_sum.descent) / 2.0 - axis_height).abs() < 0.01);
+
+        let block = crate::parser::parse_mathml(
+            r#"<math display="block"><munderover><mo>∑</mo><mi>i</mi><mi>n</mi></munderover></math>"#,
+        )
+        .unwrap()
+        .plan_render(&mut text_renderer, &style);
+        assert_eq!(block.width, display.width);
+        assert_eq!(block.height(), display.height());
+    }
+
+    #[named]
+    #[test]
+    fn spaces() {
+        let space = |width| {
+            Element::Mspace(Mspace {
+                width: Some(width),
+                ..Default::default()
+            })
+        };
+        let content = || mrow(vec![mi("x"), mo("+"), mn("1")]);
+        let highlighted = || {
+            let Element::Mpadded(mut padded) = mpadded(content()) else {
+                unreachable!()
+            };
+            padded.width = Some(PaddedLength {
+                value: PaddedValue::Length(Length::Em(1.0)),
+                relative: true,
+            });
+            padded.lspace = Some(PaddedLength {
+                value: PaddedValue::Length(Length::Em(0.5)),
+                relative: false,
+            });
+            let Element::Mstyle(mut highlighted) = mstyle(Element::Mpadded(padded)) else {
+                unreachable!()
+            };
+            highlighted.mathbackground = Some(Color::from_rgba8(255, 240, 150, 255));
+            Element::Mstyle(highlighted)
+        };
+        let font_size = 100.0;
+        let mut text_renderer = TextRenderer::new();
+        let style = StyleContext::new(font_size);
+
+        // The padding widens the content by an em, keeping its height
+        let plain = content().plan_render(&mut text_renderer, &style);
+        let highlighted_plan = highlighted().plan_render(&mut text_renderer, &style);
+        assert!((highlighted_plan.width - (plain.width + font_size)).abs() < 0.01);
+        assert_eq!(highlighted_plan.ascent, plain.ascent);
+        assert_eq!(highlighted_plan.descent, plain.descent);
+
+        // The spaces add their width between the letters, the negative one removing some
+        let letters = mrow(vec![mi("a"), mi("b"), mi("c")]).plan_render(&mut text_renderer, &style);
+        let spaced = mrow(vec![
+            mi("a"),
+            space(Length::Em(1.0)),
+            mi("b"),
+            space(Length::Em(-3.0 / 18.0)),
+            mi("c"),
+        ]);
+        let spaced_plan = spaced.plan_render(&mut text_renderer, &style);
+        let expected_width = letters.width + font_size * (1.0 - 3.0 / 18.0);
+        assert!((spaced_plan.width - expected_width).abs() < 0.01);
+
+        let whole = mrow(vec![spaced, highlighted()]);
+        let plan = whole.plan_render(&mut text_renderer, &style);
+        assert!((plan.width - (spaced_plan.width + highlighted_plan.width)).abs() < 0.01);
+
+        let img = whole.render(&mut text_renderer, &style);
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
     }
 
+    #[test]
+    fn space_dimensions() {
+        let mut text_renderer = math_font_renderer();
+        let style = StyleContext::new(100.0);
+        let x_height = text_renderer.x_height() * style.font_size;
+
+        let space = Element::Mspace(Mspace {
+            width: Some(Length::Em(2.0)),
+            height: Some(Length::Ex(1.0)),
+            depth: Some(Length::Px(-10.0)),
+        })
+        .plan_render(&mut text_renderer, &style);
+        assert_eq!(space.width, 200.0);
+        assert!((space.ascent - x_height).abs() < 0.01);
+        assert_eq!(space.descent, 0.0);
+
+        let content = || mrow(vec![mi("x"), mo("+"), mn("1")]);
+        let plain = content().plan_render(&mut text_renderer, &style);
+        let padded = |attributes: fn(&mut Mpadded), text_renderer: &mut TextRenderer| {
+            let Element::Mpadded(mut padded) = mpadded(content()) else {
+                unreachable!()
+            };
+            attributes(&mut padded);
+            Element::Mpadded(padded).plan_render(text_renderer, &style)
+        };
+        let wider = padded(
+            |padded| {
+                padded.width = Some(PaddedLength {
+                    value: PaddedValue::Length(Length::Em(1.0)),
+                    relative: true,
+                })
+            },
+            &mut text_renderer,
+        );
+        assert!((wider.width - (plain.width + 100.0)).abs() < 0.01);
+        let scaled = padded(
+            |padded| {
+                padded.width = Some(PaddedLength {
+                    value: PaddedValue::Length(Length::Percent(150.0)),
+                    relative: false,
+                });
+                padded.depth = Some(PaddedLength {
+                    value: PaddedValue::Dimension(1.0, PseudoUnit::Height),
+                    relative: false,
+                });
+            },
+            &mut text_renderer,
+        );
+        assert!((scaled.width - 1.5 * plain.width).abs() < 0.01);
+        assert_eq!(scaled.descent, plain.ascent);
+
+        // A signed percentage is still of the dimension, not added to it
+        let grown = padded(
+            |padded| {
+                padded.width = Some(PaddedLength {
+                    value: PaddedValue::Length(Length::Percent(150.0)),
+                    relative: true,
+                })
+            },
+            &mut text_renderer,
+        );
+        assert!((grown.width - 1.5 * plain.width).abs() < 0.01);
+        let shrunk = padded(
+            |padded| {
+                padded.width = Some(PaddedLength {
+                    value: PaddedValue::Length(Length::Percent(-50.0)),
+                    relative: true,
+                })
+            },
+            &mut text_renderer,
+        );
+        assert!((shrunk.width - 0.5 * plain.width).abs() < 0.01);
+
+        // Moving the content doesn't change the dimensions
+        let moved = padded(
+            |padded| {
+                padded.lspace = Some(PaddedLength {
+                    value: PaddedValue::Length(Length::Em(1.0)),
+                    relative: false,
+                });
+                padded.voffset = Some(PaddedLength {
+                    value: PaddedValue::Length(Length::Em(-1.0)),
+                    relative: false,
+                });
+            },
+            &mut text_renderer,
+        );
+        assert_eq!(moved.width, plain.width);
+        assert_eq!(moved.height(), plain.height());
+    }
+
+    #[named]
+    #[test]
+    fn enclosed() {
+        let contents = || {
+            vec![
+                mrow(vec![mi("x"), mo("="), mfrac(mn("1"), mn("2"))]),
+                mn("3"),
+                mn("1234"),
+                mi("a"),
+            ]
+        };
+        let notations = [
+            Notation::Box,
+            Notation::UpDiagonalStrike,
+            Notation::Longdiv,
+            Notation::Circle,
+        ];
+        let enclosures = || {
+            notations
+                .iter()
+                .zip(contents())
+                .map(|(notation, content)| menclose(vec![*notation], content))
+                .collect::<Vec<_>>()
+        };
+        let font_size = 100.0;
+        let mut text_renderer = TextRenderer::new();
+
+        // Lines are drawn around the content, only the strike is drawn over it
+        let whole =
+            mrow(enclosures()).plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let mut width = 0.0;
+        for ((notation, enclosure), content) in notations.iter().zip(enclosures()).zip(contents()) {
+            let enclosure =
+                enclosure.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+            let content = content.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+            if *notation == Notation::UpDiagonalStrike {
+                assert!((enclosure.width - content.width).abs() < 0.01);
+                assert!((enclosure.height() - content.height()).abs() < 0.01);
+            } else {
+                assert!(enclosure.width > content.width);
+                assert!(enclosure.ascent > content.ascent);
+                assert!(enclosure.descent >= content.descent);
+            }
+            assert!(whole.ascent >= enclosure.ascent);
+            assert!(whole.descent >= enclosure.descent);
+            width += enclosure.width;
+        }
+        assert!((whole.width - width).abs() < 0.01);
+
+        let img = mrow(enclosures()).render(&mut text_renderer, &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn enclose_padding() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        let line_width = text_renderer.math_constants().overbar_rule_thickness * font_size;
+        let margin = ENCLOSE_PADDING_RATIO * line_width + line_width;
+        let content = mi("x").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+
+        let boxed = menclose(vec![Notation::Box], mi("x"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!((boxed.width - (content.width + 2.0 * margin)).abs() < 0.01);
+        assert!((boxed.ascent - (content.ascent + margin)).abs() < 0.01);
+        assert!((boxed.descent - (content.descent + margin)).abs() < 0.01);
+
+        // Only the sides with a line are padded, strikes are drawn over the content
+        let top = menclose(vec![Notation::Top, Notation::HorizontalStrike], mi("x"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!((top.width - content.width).abs() < 0.01);
+        assert!((top.ascent - (content.ascent + margin)).abs() < 0.01);
+        assert!((top.descent - content.descent).abs() < 0.01);
+
+        let svg = menclose(vec![Notation::Top, Notation::HorizontalStrike], mi("x"))
+            .render_svg(&mut text_renderer, &StyleContext::new(font_size));
+        assert_eq!(svg.matches("stroke-width").count(), 2);
+
+        // Strikes cross the middle of the content rather than of the padded element
+        #[derive(Default)]
+        struct StrokeCanvas {
+            strokes: Vec<Path>,
+        }
+        impl MathCanvas for StrokeCanvas {
+            fn fill_path(&mut self, _path: &Path, _color: Color) {}
+            fn stroke_path(&mut self, path: &Path, _width: f32, _color: Color) {
+                self.strokes.push(path.clone());
+            }
+            fn fill_rect(&mut self, _x: f32, _y: f32, _width: f32, _height: f32, _color: Color) {}
+        }
+        let strike_start = |notation, text_renderer: &mut TextRenderer| {
+            let mut canvas = StrokeCanvas::default();
+            menclose(notation, mi("x"))
+                .plan_render(text_renderer, &StyleContext::new(font_size))
+                .draw(&mut canvas, 0.0, 0.0);
+            match canvas.strokes.last().unwrap().commands() {
+                [PathCommand::MoveTo(x, y), ..] => (*x, *y),
+                _ => panic!("expected a line"),
+            }
+        };
+        let (_, y) = strike_start(
+            vec![Notation::Top, Notation::HorizontalStrike],
+            &mut text_renderer,
+        );
+        assert!((y - (margin + content.height() / 2.0)).abs() < 0.01);
+        let (x, _) = strike_start(
+            vec![Notation::Left, Notation::VerticalStrike],
+            &mut text_renderer,
+        );
+        assert!((x - (margin + content.width / 2.0)).abs() < 0.01);
+
+        // The circle goes around the corners of the padded content
+        let circled = menclose(vec![Notation::Circle], mi("x"))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!(circled.width > content.width + 2.0 * margin);
+    }
+
     #[named]
     #[test]
     fn half_alpha_n() {
         let fraction = mfrac(msub(mi("α"), mn("n")), mn("2"));
         let font_size = 100.0;
 
-        let img = fraction.render(&mut TextRenderer::new(), font_size);
+        let img = fraction.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -521,7 +2430,7 @@ mod tests {
         let row = mrow(vec![mi("α"), mo("+"), mi("β")]);
         let font_size = 100.0;
 
-        let img = row.render(&mut TextRenderer::new(), font_size);
+        let img = row.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -533,7 +2442,7 @@ mod tests {
         let row = mrow(vec![mi("x"), mo("+"), mfrac(mn("1"), mn("2"))]);
         let font_size = 100.0;
 
-        let img = row.render(&mut TextRenderer::new(), font_size);
+        let img = row.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -545,7 +2454,7 @@ mod tests {
         let sqrt_2 = mroot(mn("2"), None);
         let font_size = 100.0;
 
-        let img = sqrt_2.render(&mut TextRenderer::new(), font_size);
+        let img = sqrt_2.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
@@ -557,12 +2466,57 @@ mod tests {
         let sqrt_2 = mroot(mroot(mn("2"), None), None);
         let font_size = 100.0;
 
-        let img = sqrt_2.render(&mut TextRenderer::new(), font_size);
+        let img = sqrt_2.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
     }
 
+    #[named]
+    #[test]
+    fn cube_root() {
+        let cube_root = mroot(mfrac(mi("x"), mn("2")), Some(mn("3")));
+        let font_size = 100.0;
+
+        let img = cube_root.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn root_index() {
+        let mut text_renderer = TextRenderer::new();
+        let font_size = 100.0;
+
+        let square_root =
+            mroot(mn("2"), None).plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let small_index = mroot(mn("2"), Some(mn("3")))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let large_index = mroot(mn("2"), Some(mfrac(mn("100"), mfrac(mn("1"), mn("3")))))
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+
+        assert!(small_index.width >= square_root.width);
+        assert!(large_index.width > small_index.width);
+        // A tall index raises the top of the root
+        assert!(large_index.ascent > square_root.ascent);
+        assert_eq!(large_index.descent, square_root.descent);
+
+        // The gap above the radicand is larger in display style
+        let constants = text_renderer.math_constants();
+        let extra_gap = (constants.radical_display_style_vertical_gap
+            - constants.radical_vertical_gap)
+            * font_size;
+        let display_root = mroot(mn("2"), None).plan_render(
+            &mut text_renderer,
+            &StyleContext {
+                displaystyle: true,
+                ..StyleContext::new(font_size)
+            },
+        );
+        assert!((display_root.ascent - (square_root.ascent + extra_gap)).abs() < 0.01);
+    }
+
     #[named]
     #[test]
     fn discriminant() {
@@ -589,13 +2543,238 @@ mod tests {
                 mrow(vec![mn("2"), mi("a")]),
             ),
         ])
-        .render(&mut TextRenderer::new(), font_size);
+        .render(&mut TextRenderer::new(), &StyleContext::new(font_size));
 
         img.save_png(format!("examples/{}.png", function_name!()))
             .unwrap();
     }
 
+    #[test]
+    fn empty_mrow() {
+        let mut text_renderer = TextRenderer::new();
+        let font_size = 100.0;
+
+        let error = mrow(vec![])
+            .try_render(&mut text_renderer, &StyleContext::new(font_size))
+            .err();
+        assert!(matches!(
+            error,
+            Some(RenderError::EmptyCanvas { width: 0, .. })
+        ));
+
+        let row = mrow(vec![
+            mi("x"),
+            mrow(vec![]),
+            mfrac(mrow(vec![]), mrow(vec![])),
+        ]);
+        assert!(
+            row.try_render(&mut text_renderer, &StyleContext::new(font_size))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn baseline_alignment() {
+        let mut text_renderer = TextRenderer::new();
+        let font_size = 100.0;
+
+        let g = mi("g").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let x = mi("x").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!(g.descent > x.descent);
+
+        let row = mrow(vec![mi("g"), mi("x")])
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert_eq!(row.ascent, g.ascent.max(x.ascent));
+        assert_eq!(row.descent, g.descent);
+    }
+
+    #[test]
+    fn identifier_variants() {
+        let mut text_renderer = math_font_renderer();
+        let style = StyleContext::new(100.0);
+        let identifier = |identifier: &str, mathvariant| {
+            Element::Mi(Mi {
+                identifier: identifier.into(),
+                mathvariant,
+            })
+        };
+
+        let italic = mi("f").plan_render(&mut text_renderer, &style);
+        let upright = mtext("f").plan_render(&mut text_renderer, &style);
+        let normal =
+            identifier("f", Some(MathVariant::Normal)).plan_render(&mut text_renderer, &style);
+        assert_ne!(italic.width, upright.width);
+        assert_eq!(normal.width, upright.width);
+
+        // Longer identifiers such as function names stay upright
+        let sin = mi("sin").plan_render(&mut text_renderer, &style);
+        assert_eq!(
+            sin.width,
+            mtext("sin").plan_render(&mut text_renderer, &style).width
+        );
+
+        let Element::Mstyle(mut bold) = mstyle(mrow(vec![mi("x"), mn("2")])) else {
+            unreachable!()
+        };
+        bold.mathvariant = Some(MathVariant::Bold);
+        let bold = Element::Mstyle(bold).plan_render(&mut text_renderer, &style);
+        let styled = mtext("𝐱𝟐").plan_render(&mut text_renderer, &style);
+        assert_eq!(bold.width, styled.width);
+    }
+
+    #[test]
+    fn token_variants() {
+        let mut text_renderer = math_font_renderer();
+        let style = StyleContext::new(100.0);
+
+        // The variant of the token overrides the inherited one
+        let number = Element::Mn(Mn {
+            number: "12".into(),
+            mathvariant: Some(MathVariant::DoubleStruck),
+        });
+        let Element::Mstyle(mut bold) = mstyle(number) else {
+            unreachable!()
+        };
+        bold.mathvariant = Some(MathVariant::Bold);
+        let number = Element::Mstyle(bold).plan_render(&mut text_renderer, &style);
+        let styled = mtext("𝟙𝟚").plan_render(&mut text_renderer, &style);
+        assert_eq!(number.width, styled.width);
+        assert_ne!(
+            number.width,
+            mn("12").plan_render(&mut text_renderer, &style).width
+        );
+
+        let text = Element::Mtext(Mtext {
+            text: "ab".into(),
+            mathvariant: Some(MathVariant::Fraktur),
+        })
+        .plan_render(&mut text_renderer, &style);
+        let styled = mtext("𝔞𝔟").plan_render(&mut text_renderer, &style);
+        assert_eq!(text.width, styled.width);
+
+        // Only the glyphs of an operator are styled, not its spacing
+        let operator = |mathvariant| {
+            Element::Mo(Mo {
+                operator: "d".into(),
+                mathvariant,
+                ..Default::default()
+            })
+        };
+        let plain = operator(None).plan_render(&mut text_renderer, &style);
+        let bold = operator(Some(MathVariant::Bold)).plan_render(&mut text_renderer, &style);
+        let plain_glyph = mtext("d").plan_render(&mut text_renderer, &style);
+        let bold_glyph = mtext("𝐝").plan_render(&mut text_renderer, &style);
+        assert_ne!(plain_glyph.width, bold_glyph.width);
+        assert!((bold.width - plain.width - (bold_glyph.width - plain_glyph.width)).abs() < 0.01);
+    }
+
     #[named]
+    #[test]
+    fn stretchy_parentheses() {
+        let fraction = mfrac(mfrac(mi("α"), mi("β")), mn("2"));
+        let whole = mrow(vec![stretchy_mo("("), fraction, stretchy_mo(")"), mo("+")]);
+        let font_size = 100.0;
+
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn stretchy_operator_covers_siblings() {
+        // Glyph variants and assemblies need a font with a MATH table
+        let text_renderers = [TextRenderer::new(), math_font_renderer()];
+        let font_size = 100.0;
+        let Element::Mo(parenthesis) = stretchy_mo("(") else {
+            unreachable!()
+        };
+        for mut text_renderer in text_renderers {
+            let normal = parenthesis.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+            for depth in 1..4 {
+                let mut fraction = mi("x");
+                for _ in 0..depth {
+                    fraction = mfrac(fraction, mn("2"));
+                }
+                let fraction =
+                    fraction.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+                let stretched = parenthesis
+                    .try_plan_with_form(
+                        &mut text_renderer,
+                        &StyleContext::new(font_size),
+                        Form::Prefix,
+                        Some((fraction.ascent, fraction.descent)),
+                    )
+                    .unwrap();
+                assert!(stretched.height() > normal.height());
+                assert!(stretched.height() >= 0.9 * fraction.height());
+            }
+        }
+    }
+
+    #[test]
+    fn operator_spacing() {
+        let mut text_renderer = TextRenderer::new();
+        let font_size = 100.0;
+        let mut width = |element: Element| {
+            element
+                .plan_render(&mut text_renderer, &StyleContext::new(font_size))
+                .width
+        };
+        let minus = width(mtext("−"));
+        let b = width(mi("b"));
+
+        // Binary minus gets medium spaces on both sides, unary minus none
+        let binary = width(mrow(vec![mi("b"), mo("−"), mi("b")]));
+        assert!((binary - (2.0 * b + minus + 2.0 * 4.0 / 18.0 * font_size)).abs() < 0.01);
+        let unary = width(mrow(vec![mo("−"), mi("b")]));
+        assert!((unary - (b + minus)).abs() < 0.01);
+
+        // Explicit attributes override the dictionary
+        let Element::Mo(mut operator) = mo("−") else {
+            unreachable!()
+        };
+        operator.form = Some(Form::Prefix);
+        operator.rspace = Some(Length::Px(10.0));
+        assert!((width(Element::Mo(operator)) - (minus + 10.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn custom_canvas() {
+        /// Canvas which only records what is drawn on it
+        #[derive(Default)]
+        struct RecordingCanvas {
+            glyphs: usize,
+            paths: usize,
+            rects: usize,
+            strokes: usize,
+        }
+        impl MathCanvas for RecordingCanvas {
+            fn fill_path(&mut self, _path: &Path, _color: Color) {
+                self.paths += 1;
+            }
+            fn stroke_path(&mut self, _path: &Path, _width: f32, _color: Color) {
+                self.strokes += 1;
+            }
+            fn fill_rect(&mut self, _x: f32, _y: f32, _width: f32, _height: f32, _color: Color) {
+                self.rects += 1;
+            }
+            fn draw_glyph_run(&mut self, glyph_run: &crate::canvas::GlyphRun, _x: f32, _y: f32) {
+                self.glyphs += glyph_run.glyphs.len();
+            }
+        }
+
+        let mut canvas = RecordingCanvas::default();
+        mrow(vec![mfrac(mi("α"), mi("β")), mroot(mn("2"), None)])
+            .plan_render(&mut TextRenderer::new(), &StyleContext::new(100.0))
+            .draw(&mut canvas, 0.0, 0.0);
+        // The radical sign is either a glyph, or scaled outlines for fonts without a MATH table
+        assert_eq!(canvas.glyphs + canvas.paths, 4);
+        // Bars of the fraction and the radical
+        assert_eq!(canvas.rects, 2);
+        assert_eq!(canvas.strokes, 0);
+    }
+
     #[bench]
     fn discriminant_bench(b: &mut Bencher) {
         let font_size = 100.0;
@@ -623,6 +2802,6 @@ mod tests {
         ]);
         let mut text_renderer = TextRenderer::new();
 
-        b.iter(|| expression.render(&mut text_renderer, font_size));
+        b.iter(|| expression.render(&mut text_renderer, &StyleContext::new(font_size)));
     }
 }
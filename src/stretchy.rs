@@ -0,0 +1,307 @@
+//! Vertical stretching of operators such as parentheses, brackets and braces, and the larger
+//! display style variants of large operators such as sums.
+//!
+//! Glyphs are stretched using the larger variants or the glyph assembly from the MATH table of the
+//! font. Without those, the outlines are scaled vertically.
+
+use skrifa::instance::{LocationRef, Size};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+
+use crate::canvas::{GlyphRun, MathCanvas, PositionedGlyph};
+use crate::math_table::{GlyphConstruction, GlyphPart};
+use crate::render::{RenderError, RenderingPlan};
+use crate::style::StyleContext;
+use crate::text_rendering::{TextRenderer, ink_extents};
+
+/// Bound on the repetitions of extenders, in case they are too small to make any progress
+const MAX_EXTENDER_REPEATS: usize = 100;
+
+/// Plan the operator so that it covers at least `ascent` above and `descent` below the baseline.
+///
+/// Symmetric operators are centered on the math axis, the others on the covered area. The operator
+/// is never shrunk, it keeps its normal size if that is already enough.
+pub fn plan_stretched_operator(
+    text_renderer: &mut TextRenderer,
+    operator: &str,
+    style: &StyleContext,
+    ascent: f32,
+    descent: f32,
+    symmetric: bool,
+) -> Result<RenderingPlan, RenderError> {
+    let font_size = style.font_size;
+    // Height of the center of the stretched operator above the baseline
+    let (center, target) = if symmetric {
+        let axis = text_renderer.math_constants().axis_height * font_size;
+        (axis, 2.0 * f32::max(ascent - axis, descent + axis))
+    } else {
+        ((ascent - descent) / 2.0, ascent + descent)
+    };
+
+    let mut characters = operator.chars();
+    let construction = match (characters.next(), characters.next()) {
+        (Some(character), None) => text_renderer
+            .math_glyph_id(character)
+            .and_then(|glyph_id| text_renderer.math_variants().vertical.get(&glyph_id)),
+        _ => None,
+    };
+    if let (Some(construction), Some(font)) = (construction, text_renderer.math_font()) {
+        let min_connector_overlap = text_renderer.math_variants().min_connector_overlap;
+        let glyph_run = GlyphRun {
+            font: font.clone(),
+            font_size,
+            normalized_coords: vec![],
+            glyphs: vec![],
+            color: style.color,
+        };
+        if let Some(plan) = plan_construction(
+            glyph_run,
+            construction,
+            min_connector_overlap * font_size,
+            target,
+            center,
+        ) {
+            return Ok(plan);
+        }
+    }
+    plan_scaled_operator(text_renderer, operator, style, target, center)
+}
+
+/// Use the smallest variant which is large enough, or else an assembly or the largest variant
+fn plan_construction(
+    mut glyph_run: GlyphRun,
+    construction: &GlyphConstruction,
+    min_connector_overlap: f32,
+    target: f32,
+    center: f32,
+) -> Option<RenderingPlan> {
+    let font_size = glyph_run.font_size;
+    let variant = construction
+        .variants
+        .iter()
+        .find(|variant| variant.advance * font_size >= target);
+    let glyphs = match variant {
+        Some(variant) => vec![variant.glyph_id],
+        None if !construction.assembly.is_empty() => {
+            return plan_assembly(
+                glyph_run,
+                &construction.assembly,
+                min_connector_overlap,
+                target,
+                center,
+            );
+        }
+        None => vec![construction.variants.last()?.glyph_id],
+    };
+    glyph_run.glyphs = glyphs
+        .into_iter()
+        .map(|id| PositionedGlyph { id, x: 0.0, y: 0.0 })
+        .collect();
+    Some(plan_centered_glyph(glyph_run, center))
+}
+
+/// Plan the single glyph of the run with the middle of its ink at `center` above the baseline
+fn plan_centered_glyph(mut glyph_run: GlyphRun, center: f32) -> RenderingPlan {
+    let (ascent, descent) = ink_extents(std::slice::from_ref(&glyph_run));
+    let width = advance_width(&glyph_run, glyph_run.glyphs[0].id);
+
+    let height = ascent + descent;
+    let shift = center - (ascent - descent) / 2.0;
+    glyph_run.glyphs[0].y = -shift;
+    plan_glyph_run(
+        glyph_run,
+        height / 2.0 + center,
+        height / 2.0 - center,
+        width,
+    )
+}
+
+/// Glyph of a large operator (e.g. `∑` or `∫`) in display style: its first variant which is at
+/// least `DisplayOperatorMinHeight` and √2 times the normal glyph tall, or its largest one. `None`
+/// if the font has no variants for it.
+///
+/// See <https://w3c.github.io/mathml-core/#layout-of-operators>
+pub fn display_variant(text_renderer: &TextRenderer, operator: &str) -> Option<u32> {
+    let mut characters = operator.chars();
+    let glyph_id = match (characters.next(), characters.next()) {
+        (Some(character), None) => text_renderer.math_glyph_id(character)?,
+        _ => return None,
+    };
+    let variants = &text_renderer
+        .math_variants()
+        .vertical
+        .get(&glyph_id)?
+        .variants;
+    // The first variant is the glyph itself
+    let normal_height = variants.first()?.advance;
+    let min_height = f32::max(
+        text_renderer.math_constants().display_operator_min_height,
+        std::f32::consts::SQRT_2 * normal_height,
+    );
+    variants
+        .iter()
+        .find(|variant| variant.advance >= min_height)
+        .or(variants.last())
+        .map(|variant| variant.glyph_id)
+}
+
+/// Plan a large operator in display style, centered on the math axis.
+///
+/// Unlike stretchy operators, large operators are never assembled from parts, and they keep their
+/// normal size in fonts without glyph variants.
+pub fn plan_large_operator(
+    text_renderer: &mut TextRenderer,
+    operator: &str,
+    style: &StyleContext,
+) -> Result<RenderingPlan, RenderError> {
+    let variant = display_variant(text_renderer, operator);
+    let (Some(glyph_id), Some(font)) = (variant, text_renderer.math_font()) else {
+        return text_renderer.plan_render_text(operator.into(), style);
+    };
+    let glyph_run = GlyphRun {
+        font: font.clone(),
+        font_size: style.font_size,
+        normalized_coords: vec![],
+        glyphs: vec![PositionedGlyph {
+            id: glyph_id,
+            x: 0.0,
+            y: 0.0,
+        }],
+        color: style.color,
+    };
+    let axis = text_renderer.math_constants().axis_height * style.font_size;
+    Ok(plan_centered_glyph(glyph_run, axis))
+}
+
+/// Stack the parts of an assembly from the bottom up, repeating the extenders as many times as
+/// needed to reach the target size
+fn plan_assembly(
+    mut glyph_run: GlyphRun,
+    assembly: &[GlyphPart],
+    min_connector_overlap: f32,
+    target: f32,
+    center: f32,
+) -> Option<RenderingPlan> {
+    let font_size = glyph_run.font_size;
+    let mut parts = vec![];
+    for repeats in 0..=MAX_EXTENDER_REPEATS {
+        parts = assembly
+            .iter()
+            .flat_map(|part| {
+                let count = if part.extender { repeats } else { 1 };
+                std::iter::repeat_n(*part, count)
+            })
+            .collect::<Vec<_>>();
+        let connections = parts.len().saturating_sub(1) as f32;
+        let advance = parts.iter().map(|part| part.full_advance).sum::<f32>() * font_size;
+        if advance - connections * min_connector_overlap >= target {
+            break;
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    // Spread the overlap evenly between connections, within the length of the connectors
+    let advance = parts.iter().map(|part| part.full_advance).sum::<f32>() * font_size;
+    let max_overlap = parts
+        .windows(2)
+        .map(|pair| f32::min(pair[0].end_connector_length, pair[1].start_connector_length))
+        .fold(f32::INFINITY, f32::min)
+        * font_size;
+    let overlap = if parts.len() > 1 {
+        ((advance - target) / (parts.len() - 1) as f32)
+            .min(max_overlap)
+            .max(min_connector_overlap)
+    } else {
+        0.0
+    };
+    let height = advance - overlap * (parts.len() - 1) as f32;
+    let descent = height / 2.0 - center;
+
+    let mut bottom = 0.0;
+    let mut width = 0.0_f32;
+    for part in &parts {
+        let mut part_run = glyph_run.clone();
+        part_run.glyphs = vec![PositionedGlyph {
+            id: part.glyph_id,
+            x: 0.0,
+            y: 0.0,
+        }];
+        let (_, part_descent) = ink_extents(&[part_run]);
+        // The bottom of the ink of the part is placed at the current height
+        glyph_run.glyphs.push(PositionedGlyph {
+            id: part.glyph_id,
+            x: 0.0,
+            y: descent - bottom - part_descent,
+        });
+        width = width.max(advance_width(&glyph_run, part.glyph_id));
+        bottom += part.full_advance * font_size - overlap;
+    }
+    Some(plan_glyph_run(
+        glyph_run,
+        height / 2.0 + center,
+        descent,
+        width,
+    ))
+}
+
+/// Fallback for fonts without glyph constructions: scale the outlines of the text vertically
+fn plan_scaled_operator(
+    text_renderer: &mut TextRenderer,
+    operator: &str,
+    style: &StyleContext,
+    target: f32,
+    center: f32,
+) -> Result<RenderingPlan, RenderError> {
+    let (glyph_runs, width) = text_renderer.shape_text(operator, style.font_size)?;
+    let (ascent, descent) = ink_extents(&glyph_runs);
+    let height = ascent + descent;
+    if height <= 0.0 || height >= target {
+        return text_renderer.plan_render_text(operator.into(), style);
+    }
+    let color = style.color;
+
+    let scale = target / height;
+    let stretched_ascent = target / 2.0 + center;
+    let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+        for glyph_run in &glyph_runs {
+            for glyph in &glyph_run.glyphs {
+                // Outlines are scaled from the top of the ink, which is placed at the top
+                if let Some(path) = glyph_run.glyph_path(glyph, 0.0, ascent) {
+                    canvas.fill_path(&path.transformed(1.0, scale, x, y), color);
+                }
+            }
+        }
+    };
+    Ok(RenderingPlan {
+        callback: Box::new(callback),
+        ascent: stretched_ascent,
+        descent: target - stretched_ascent,
+        width,
+    })
+}
+
+/// Plan glyphs positioned relative to the baseline
+fn plan_glyph_run(glyph_run: GlyphRun, ascent: f32, descent: f32, width: f32) -> RenderingPlan {
+    let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+        canvas.draw_glyph_run(&glyph_run, x, y + ascent);
+    };
+    RenderingPlan {
+        callback: Box::new(callback),
+        ascent,
+        descent,
+        width,
+    }
+}
+
+fn advance_width(glyph_run: &GlyphRun, glyph_id: u32) -> f32 {
+    let font = &glyph_run.font;
+    FontRef::from_index(font.data.as_ref(), font.index)
+        .ok()
+        .and_then(|font_ref| {
+            font_ref
+                .glyph_metrics(Size::new(glyph_run.font_size), LocationRef::default())
+                .advance_width(GlyphId::new(glyph_id))
+        })
+        .unwrap_or_default()
+}
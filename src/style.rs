@@ -0,0 +1,152 @@
+//! Style inherited by elements from their ancestors, such as `<mstyle>` and `<math>`.
+//!
+//! Each element is planned with the [`StyleContext`] of its parent, and passes an updated copy to
+//! its children, e.g. scripts are drawn smaller and never in display style.
+
+use crate::canvas::Color;
+use crate::math_table::MathConstants;
+use crate::mml_types::MathVariant;
+
+/// Default `scriptminsize` of 8pt, in pixels
+pub const DEFAULT_SCRIPT_MIN_SIZE: f32 = 8.0 * 96.0 / 72.0;
+/// Scaling of the font size per script level beyond those given by the font, as for the default
+/// `scriptsizemultiplier`
+const SCRIPT_SIZE_MULTIPLIER: f32 = 0.71;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StyleContext {
+    /// Font size in pixels, as set by `mathsize` and reduced in scripts
+    pub font_size: f32,
+    /// Whether the layout is for a block of its own, with larger operators and fractions, rather
+    /// than within a line of text
+    pub displaystyle: bool,
+    /// Nesting depth of scripts, 0 outside of any script
+    pub scriptlevel: i32,
+    /// Smallest font size in pixels that increasing the script level scales down to
+    pub scriptminsize: f32,
+    /// Color of the text and lines
+    pub color: Color,
+    /// Color painted behind the element which sets it, its children are drawn over it
+    pub background: Option<Color>,
+    /// Style of the letters of identifiers, e.g. bold or double-struck
+    pub mathvariant: Option<MathVariant>,
+}
+
+impl StyleContext {
+    /// Inline style in black, at the given font size
+    pub fn new(font_size: f32) -> Self {
+        Self {
+            font_size,
+            displaystyle: false,
+            scriptlevel: 0,
+            scriptminsize: DEFAULT_SCRIPT_MIN_SIZE,
+            color: Color::BLACK,
+            background: None,
+            mathvariant: None,
+        }
+    }
+
+    /// Style of the scripts of an element: inline and one script level deeper
+    pub fn script(&self, constants: &MathConstants) -> Self {
+        Self {
+            displaystyle: false,
+            ..self
+                .child()
+                .with_scriptlevel(self.scriptlevel + 1, constants)
+        }
+    }
+
+    /// Same style at another script level, with the font size scaled accordingly but not reduced
+    /// below `scriptminsize`
+    pub fn with_scriptlevel(&self, scriptlevel: i32, constants: &MathConstants) -> Self {
+        let ratio = scale_factor(self.scriptlevel, scriptlevel, constants);
+        let font_size = if ratio < 1.0 {
+            // A font which is already smaller than the minimum is not enlarged
+            (self.font_size * ratio).max(self.scriptminsize.min(self.font_size))
+        } else {
+            self.font_size * ratio
+        };
+        Self {
+            font_size,
+            scriptlevel,
+            ..*self
+        }
+    }
+
+    /// Style inherited by the children of an element
+    pub fn child(&self) -> Self {
+        Self {
+            background: None,
+            ..*self
+        }
+    }
+}
+
+/// Ratio of the font sizes at two script levels, as specified by MathML Core: the first two levels
+/// are scaled by the percentages of the font, the next ones by [`SCRIPT_SIZE_MULTIPLIER`].
+///
+/// See <https://w3c.github.io/mathml-core/#the-math-script-level-property>
+fn scale_factor(from: i32, to: i32, constants: &MathConstants) -> f32 {
+    if to < from {
+        return 1.0 / scale_factor(to, from, constants);
+    }
+    let script = constants.script_percent_scale_down;
+    let script_script = constants.script_script_percent_scale_down;
+    let (ratio, levels) = if from == to {
+        (1.0, 0)
+    } else if from <= 0 && to >= 2 {
+        (script_script, to - from - 2)
+    } else if from == 1 {
+        (script_script / script, to - from - 1)
+    } else if to == 1 {
+        (script, to - from - 1)
+    } else {
+        (1.0, to - from)
+    };
+    ratio * SCRIPT_SIZE_MULTIPLIER.powi(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn script_sizes() {
+        let constants = MathConstants {
+            script_percent_scale_down: 0.7,
+            script_script_percent_scale_down: 0.55,
+            ..Default::default()
+        };
+        let style = StyleContext::new(100.0);
+        let script = style.script(&constants);
+        let script_script = script.script(&constants);
+        assert_eq!(script.scriptlevel, 1);
+        assert_close(script.font_size, 70.0);
+        assert_close(script_script.font_size, 55.0);
+        assert_close(script_script.script(&constants).font_size, 55.0 * 0.71);
+        assert_close(style.with_scriptlevel(2, &constants).font_size, 55.0);
+        assert_close(
+            script_script.with_scriptlevel(0, &constants).font_size,
+            100.0,
+        );
+    }
+
+    #[test]
+    fn script_min_size() {
+        let constants = MathConstants::default();
+        let mut style = StyleContext::new(20.0);
+        for _ in 0..5 {
+            style = style.script(&constants);
+        }
+        assert_eq!(style.scriptlevel, 5);
+        assert_eq!(style.font_size, DEFAULT_SCRIPT_MIN_SIZE);
+
+        // Fonts below the minimum keep their size
+        let small = StyleContext::new(5.0).script(&constants);
+        assert_eq!(small.font_size, 5.0);
+    }
+}
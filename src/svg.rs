@@ -0,0 +1,275 @@
+//! SVG output backend, for resolution independent equations.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use skrifa::instance::NormalizedCoord;
+
+use crate::canvas::{
+    Color, GlyphRun, MathCanvas, Path, PathCommand, PositionedGlyph, format_number as number,
+};
+
+type GlyphKey = (u64, u32, u32, u32, Vec<NormalizedCoord>);
+
+/// Canvas which records everything drawn on it as SVG elements.
+///
+/// By default, each distinct glyph is defined once in `<defs>` and then drawn with `<use>`.
+pub struct SvgCanvas {
+    defs: String,
+    body: String,
+    /// ids of the glyph outlines already defined, keyed by font, font index, glyph id, size and
+    /// variation coordinates
+    glyph_ids: HashMap<GlyphKey, usize>,
+    reuse_glyphs: bool,
+}
+
+impl Default for SvgCanvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgCanvas {
+    pub fn new() -> Self {
+        Self {
+            defs: String::new(),
+            body: String::new(),
+            glyph_ids: HashMap::new(),
+            reuse_glyphs: true,
+        }
+    }
+
+    /// Whether glyphs should be defined once and reused, or drawn inline as separate paths
+    pub fn with_glyph_reuse(mut self, reuse_glyphs: bool) -> Self {
+        self.reuse_glyphs = reuse_glyphs;
+        self
+    }
+
+    /// Wrap the recorded elements in an `<svg>` element of the size of the rendered element.
+    ///
+    /// The `vertical-align` style shifts the image down by its descent, so that the baseline of
+    /// the equation lines up with the surrounding text when it is placed inline.
+    pub fn finish(self, width: f32, ascent: f32, descent: f32) -> String {
+        let height = ascent + descent;
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}" viewBox="0 0 {w} {h}" style="vertical-align: {baseline}px">"#,
+            w = number(width),
+            h = number(height),
+            baseline = number(-descent),
+        );
+        if !self.defs.is_empty() {
+            write!(svg, "<defs>{}</defs>", self.defs).unwrap();
+        }
+        svg.push_str(&self.body);
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+impl MathCanvas for SvgCanvas {
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        if path.is_empty() {
+            return;
+        }
+        write!(
+            self.body,
+            r#"<path d="{}"{}/>"#,
+            path_data(path),
+            paint_attributes("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn stroke_path(&mut self, path: &Path, width: f32, color: Color) {
+        if path.is_empty() {
+            return;
+        }
+        write!(
+            self.body,
+            r#"<path d="{}" fill="none" stroke-width="{}"{}/>"#,
+            path_data(path),
+            number(width),
+            paint_attributes("stroke", color)
+        )
+        .unwrap();
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        write!(
+            self.body,
+            r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+            number(x),
+            number(y),
+            number(width),
+            number(height),
+            paint_attributes("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn draw_glyph_run(&mut self, glyph_run: &GlyphRun, x: f32, y: f32) {
+        if !self.reuse_glyphs {
+            for glyph in &glyph_run.glyphs {
+                if let Some(path) = glyph_run.glyph_path(glyph, x, y) {
+                    self.fill_path(&path, glyph_run.color);
+                }
+            }
+            return;
+        }
+        for glyph in &glyph_run.glyphs {
+            let key = (
+                glyph_run.font.data.id(),
+                glyph_run.font.index,
+                glyph.id,
+                glyph_run.font_size.to_bits(),
+                glyph_run.normalized_coords.clone(),
+            );
+            let id = match self.glyph_ids.get(&key) {
+                Some(id) => *id,
+                None => {
+                    // Define the outline at the origin, it is positioned by <use>
+                    let origin = PositionedGlyph {
+                        x: 0.0,
+                        y: 0.0,
+                        ..*glyph
+                    };
+                    let Some(path) = glyph_run.glyph_path(&origin, 0.0, 0.0) else {
+                        continue;
+                    };
+                    let id = self.glyph_ids.len();
+                    write!(self.defs, r#"<path id="g{id}" d="{}"/>"#, path_data(&path)).unwrap();
+                    self.glyph_ids.insert(key, id);
+                    id
+                }
+            };
+            write!(
+                self.body,
+                r##"<use xlink:href="#g{id}" x="{}" y="{}"{}/>"##,
+                number(x + glyph.x),
+                number(y + glyph.y),
+                paint_attributes("fill", glyph_run.color)
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn path_data(path: &Path) -> String {
+    let mut data = String::new();
+    for command in path.commands() {
+        if !data.is_empty() {
+            data.push(' ');
+        }
+        match *command {
+            PathCommand::MoveTo(x, y) => write!(data, "M{} {}", number(x), number(y)),
+            PathCommand::LineTo(x, y) => write!(data, "L{} {}", number(x), number(y)),
+            PathCommand::QuadTo(cx, cy, x, y) => write!(
+                data,
+                "Q{} {} {} {}",
+                number(cx),
+                number(cy),
+                number(x),
+                number(y)
+            ),
+            PathCommand::CurveTo(cx0, cy0, cx1, cy1, x, y) => write!(
+                data,
+                "C{} {} {} {} {} {}",
+                number(cx0),
+                number(cy0),
+                number(cx1),
+                number(cy1),
+                number(x),
+                number(y)
+            ),
+            PathCommand::Close => write!(data, "Z"),
+        }
+        .unwrap();
+    }
+    data
+}
+
+/// `fill`/`stroke` attributes, black being the SVG default fill is omitted
+fn paint_attributes(property: &str, color: Color) -> String {
+    let Color { r, g, b, a } = color;
+    let mut attributes = String::new();
+    if property != "fill" || (r, g, b) != (0, 0, 0) {
+        write!(attributes, r##" {property}="#{r:02x}{g:02x}{b:02x}""##).unwrap();
+    }
+    if a != 255 {
+        write!(
+            attributes,
+            r#" {property}-opacity="{}""#,
+            number(f32::from(a) / 255.0)
+        )
+        .unwrap();
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mml_types::{mfrac, mi, mn, mrow};
+    use crate::render::Render;
+    use crate::style::StyleContext;
+    use crate::text_rendering::TextRenderer;
+
+    #[test]
+    fn fraction_svg() {
+        let svg = mfrac(mrow(vec![mi("x"), mi("x")]), mn("2"))
+            .render_svg(&mut TextRenderer::new(), &StyleContext::new(100.0));
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("viewBox=\"0 0 "));
+        assert!(svg.contains("vertical-align: -"));
+        // the bar of the fraction
+        assert_eq!(svg.matches("<rect").count(), 1);
+        // the glyph "x" is only defined once
+        assert_eq!(svg.matches("<path id=").count(), 2);
+        assert_eq!(svg.matches("<use").count(), 3);
+    }
+
+    #[test]
+    fn inline_glyphs() {
+        let plan = mi("x").plan_render(&mut TextRenderer::new(), &StyleContext::new(100.0));
+        let mut canvas = SvgCanvas::new().with_glyph_reuse(false);
+        plan.draw(&mut canvas, 0.0, 0.0);
+        let svg = canvas.finish(plan.width, plan.ascent, plan.descent);
+        assert!(!svg.contains("<defs>"));
+        assert_eq!(svg.matches("<path d=").count(), 1);
+    }
+
+    /// Canvas keeping the glyph runs drawn on it
+    struct GlyphRunRecorder(Vec<GlyphRun>);
+
+    impl MathCanvas for GlyphRunRecorder {
+        fn fill_path(&mut self, _: &Path, _: Color) {}
+        fn stroke_path(&mut self, _: &Path, _: f32, _: Color) {}
+        fn fill_rect(&mut self, _: f32, _: f32, _: f32, _: f32, _: Color) {}
+        fn draw_glyph_run(&mut self, glyph_run: &GlyphRun, _: f32, _: f32) {
+            self.0.push(glyph_run.clone());
+        }
+    }
+
+    #[test]
+    fn variation_coordinates() {
+        let mut recorder = GlyphRunRecorder(vec![]);
+        mi("x")
+            .plan_render(&mut TextRenderer::new(), &StyleContext::new(100.0))
+            .draw(&mut recorder, 0.0, 0.0);
+        let glyph_run = recorder.0.pop().unwrap();
+        let mut varied_glyph_run = glyph_run.clone();
+        varied_glyph_run.normalized_coords = vec![NormalizedCoord::from_f32(0.5)];
+
+        // The same glyph at other coordinates of a variable font has another outline
+        let mut canvas = SvgCanvas::new();
+        canvas.draw_glyph_run(&glyph_run, 0.0, 0.0);
+        canvas.draw_glyph_run(&varied_glyph_run, 100.0, 0.0);
+        canvas.draw_glyph_run(&glyph_run, 200.0, 0.0);
+        let svg = canvas.finish(300.0, 100.0, 0.0);
+        assert_eq!(svg.matches("<path id=").count(), 2);
+        assert_eq!(svg.matches("<use").count(), 3);
+    }
+}
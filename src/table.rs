@@ -0,0 +1,501 @@
+//! Layout of tables (`<mtable>`), used for matrices and case splits.
+//!
+//! Cells are placed on a grid, skipping the slots taken by cells spanning several rows. Columns are
+//! as wide as their widest cell and rows as tall as their tallest cell, then the cells spanning
+//! several rows or columns enlarge them if they don't fit. The table is centered on the math axis.
+
+use crate::canvas::{Color, MathCanvas};
+use crate::mml_types::{ColumnAlign, Length, LineStyle, Mtable, RowAlign};
+use crate::render::{Render, RenderError, RenderingPlan};
+use crate::style::StyleContext;
+use crate::text_rendering::TextRenderer;
+
+/// Spacing between columns when not specified
+const DEFAULT_COLUMN_SPACING: Length = Length::Em(0.8);
+/// Spacing between rows when not specified
+const DEFAULT_ROW_SPACING: Length = Length::Ex(1.0);
+/// Spacing between the frame and the cells, horizontally
+const FRAME_SPACING_X: Length = Length::Em(0.4);
+/// Spacing between the frame and the cells, vertically
+const FRAME_SPACING_Y: Length = Length::Ex(0.5);
+/// Length of the dashes of dashed lines, and of the gaps between them, in line widths
+const DASH_RATIO: f32 = 4.0;
+
+/// Value for the row or column at `index`, the last value being repeated for the remaining ones
+fn nth_or_last<T: Copy>(values: &[T], index: usize) -> Option<T> {
+    values.get(index).or(values.last()).copied()
+}
+
+/// Planned cell with its position on the grid and its resolved alignments
+struct GridCell {
+    plan: RenderingPlan,
+    row: usize,
+    column: usize,
+    rowspan: usize,
+    columnspan: usize,
+    columnalign: ColumnAlign,
+    rowalign: RowAlign,
+}
+
+/// Line of a table, as the rectangle that it fills
+struct Line {
+    style: LineStyle,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Line {
+    fn draw(&self, canvas: &mut dyn MathCanvas, x: f32, y: f32, line_width: f32, color: Color) {
+        let (x, y) = (x + self.x, y + self.y);
+        match self.style {
+            LineStyle::None => {}
+            LineStyle::Solid => canvas.fill_rect(x, y, self.width, self.height, color),
+            LineStyle::Dashed => {
+                // Dashes along the longer side, the line always starts and ends with a dash
+                let dash = DASH_RATIO * line_width;
+                let horizontal = self.width > self.height;
+                let length = if horizontal { self.width } else { self.height };
+                let mut start = 0.0;
+                while start < length {
+                    let end = (start + dash).min(length);
+                    if horizontal {
+                        canvas.fill_rect(x + start, y, end - start, self.height, color);
+                    } else {
+                        canvas.fill_rect(x, y + start, self.width, end - start, color);
+                    }
+                    start += 2.0 * dash;
+                }
+            }
+        }
+    }
+}
+
+impl Render for Mtable {
+    fn try_plan_render(
+        &self,
+        text_renderer: &mut TextRenderer,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let font_size = style.font_size;
+        let constants = text_renderer.math_constants();
+        let axis_height = constants.axis_height * font_size;
+        let line_width = constants.fraction_rule_thickness * font_size;
+        let x_height = text_renderer.x_height() * font_size;
+        // Cells are laid out in inline style, even in a display formula
+        let cell_style = StyleContext {
+            displaystyle: false,
+            ..style.child()
+        };
+
+        // Index of the cell occupying each slot of the grid, by row then column
+        let row_count = self.rows.len();
+        let mut grid: Vec<Vec<Option<usize>>> = vec![vec![]; row_count];
+        let mut cells = vec![];
+        for (row_index, row) in self.rows.iter().enumerate() {
+            let mut column = 0;
+            for cell in &row.cells {
+                while grid[row_index].get(column).copied().flatten().is_some() {
+                    column += 1;
+                }
+                let rowspan = cell.rowspan.clamp(1, row_count - row_index);
+                let columnspan = cell.columnspan.max(1);
+                for slots in &mut grid[row_index..row_index + rowspan] {
+                    if slots.len() < column + columnspan {
+                        slots.resize(column + columnspan, None);
+                    }
+                    slots[column..column + columnspan].fill(Some(cells.len()));
+                }
+
+                let columnalign = cell
+                    .columnalign
+                    .or_else(|| nth_or_last(&row.columnalign, column))
+                    .or_else(|| nth_or_last(&self.columnalign, column))
+                    .unwrap_or_default();
+                let rowalign = cell
+                    .rowalign
+                    .or(row.rowalign)
+                    .or_else(|| nth_or_last(&self.rowalign, row_index))
+                    .unwrap_or_default();
+                cells.push(GridCell {
+                    plan: cell.content.try_plan_render(text_renderer, &cell_style)?,
+                    row: row_index,
+                    column,
+                    rowspan,
+                    columnspan,
+                    columnalign,
+                    rowalign,
+                });
+                column += columnspan;
+            }
+        }
+        let column_count = grid.iter().map(Vec::len).max().unwrap_or(0);
+
+        let column_spacing = (0..column_count.saturating_sub(1))
+            .map(|index| {
+                nth_or_last(&self.columnspacing, index)
+                    .unwrap_or(DEFAULT_COLUMN_SPACING)
+                    .to_px(font_size, x_height)
+            })
+            .collect::<Vec<_>>();
+        let row_spacing = (0..row_count.saturating_sub(1))
+            .map(|index| {
+                nth_or_last(&self.rowspacing, index)
+                    .unwrap_or(DEFAULT_ROW_SPACING)
+                    .to_px(font_size, x_height)
+            })
+            .collect::<Vec<_>>();
+
+        // Columns are as wide as their cells, the extra width of spanning cells is shared evenly
+        let mut widths = vec![0.0_f32; column_count];
+        for cell in cells.iter().filter(|cell| cell.columnspan == 1) {
+            widths[cell.column] = widths[cell.column].max(cell.plan.width);
+        }
+        for cell in cells.iter().filter(|cell| cell.columnspan > 1) {
+            let columns = cell.column..cell.column + cell.columnspan;
+            let spanned_width = widths[columns.clone()].iter().sum::<f32>()
+                + column_spacing[cell.column..columns.end - 1]
+                    .iter()
+                    .sum::<f32>();
+            let extra = (cell.plan.width - spanned_width) / cell.columnspan as f32;
+            if extra > 0.0 {
+                widths[columns].iter_mut().for_each(|width| *width += extra);
+            }
+        }
+
+        // Rows fit the cells aligned on their baseline first, then grow to fit the other cells.
+        // Cells aligned on the axis share the axis of the row, hence its baseline.
+        let mut ascents = vec![0.0_f32; row_count];
+        let mut descents = vec![0.0_f32; row_count];
+        let (baseline_cells, other_cells): (Vec<_>, Vec<_>) = cells
+            .iter()
+            .filter(|cell| cell.rowspan == 1)
+            .partition(|cell| matches!(cell.rowalign, RowAlign::Baseline | RowAlign::Axis));
+        for cell in baseline_cells {
+            ascents[cell.row] = ascents[cell.row].max(cell.plan.ascent);
+            descents[cell.row] = descents[cell.row].max(cell.plan.descent);
+        }
+        for cell in other_cells {
+            let extra = cell.plan.height() - (ascents[cell.row] + descents[cell.row]);
+            if extra > 0.0 {
+                match cell.rowalign {
+                    RowAlign::Top => descents[cell.row] += extra,
+                    RowAlign::Bottom => ascents[cell.row] += extra,
+                    _ => {
+                        ascents[cell.row] += extra / 2.0;
+                        descents[cell.row] += extra / 2.0;
+                    }
+                }
+            }
+        }
+        for cell in cells.iter().filter(|cell| cell.rowspan > 1) {
+            let rows = cell.row..cell.row + cell.rowspan;
+            let spanned_height = rows
+                .clone()
+                .map(|row| ascents[row] + descents[row])
+                .sum::<f32>()
+                + row_spacing[cell.row..rows.end - 1].iter().sum::<f32>();
+            let extra = (cell.plan.height() - spanned_height) / cell.rowspan as f32;
+            if extra > 0.0 {
+                descents[rows]
+                    .iter_mut()
+                    .for_each(|descent| *descent += extra);
+            }
+        }
+
+        // Left and top edges of the columns and rows
+        let framed = self.frame != LineStyle::None;
+        let (frame_x, frame_y) = if framed {
+            (
+                FRAME_SPACING_X.to_px(font_size, x_height),
+                FRAME_SPACING_Y.to_px(font_size, x_height),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let mut column_xs = Vec::with_capacity(column_count);
+        let mut x = frame_x;
+        for (column, width) in widths.iter().enumerate() {
+            column_xs.push(x);
+            x += width + column_spacing.get(column).copied().unwrap_or_default();
+        }
+        let width = x + frame_x;
+        let mut row_ys = Vec::with_capacity(row_count);
+        let mut y = frame_y;
+        for row in 0..row_count {
+            row_ys.push(y);
+            y += ascents[row] + descents[row] + row_spacing.get(row).copied().unwrap_or_default();
+        }
+        let height = y + frame_y;
+
+        let cells = cells
+            .into_iter()
+            .map(|cell| {
+                let last_column = cell.column + cell.columnspan - 1;
+                let last_row = cell.row + cell.rowspan - 1;
+                let left = column_xs[cell.column];
+                let right = column_xs[last_column] + widths[last_column];
+                let top = row_ys[cell.row];
+                let bottom = row_ys[last_row] + ascents[last_row] + descents[last_row];
+                let x = match cell.columnalign {
+                    ColumnAlign::Left => left,
+                    ColumnAlign::Center => (left + right - cell.plan.width) / 2.0,
+                    ColumnAlign::Right => right - cell.plan.width,
+                };
+                let y = match cell.rowalign {
+                    RowAlign::Top => top,
+                    RowAlign::Bottom => bottom - cell.plan.height(),
+                    RowAlign::Center => (top + bottom - cell.plan.height()) / 2.0,
+                    RowAlign::Baseline | RowAlign::Axis => {
+                        top + ascents[cell.row] - cell.plan.ascent
+                    }
+                };
+                (cell.plan, x, y)
+            })
+            .collect::<Vec<_>>();
+
+        // Lines are centered in the spacing between rows and columns. They stop at the boundaries
+        // of the table and are interrupted by cells spanning over them.
+        let column_bounds = (0..=column_count)
+            .map(|column| match column {
+                0 => 0.0,
+                _ if column == column_count => width,
+                _ => column_xs[column] - column_spacing[column - 1] / 2.0,
+            })
+            .collect::<Vec<_>>();
+        let row_bounds = (0..=row_count)
+            .map(|row| match row {
+                0 => 0.0,
+                _ if row == row_count => height,
+                _ => row_ys[row] - row_spacing[row - 1] / 2.0,
+            })
+            .collect::<Vec<_>>();
+        let slot = |row: usize, column: usize| grid[row].get(column).copied().flatten();
+        let mut lines = vec![];
+        for column in 0..column_count.saturating_sub(1) {
+            let style = nth_or_last(&self.columnlines, column).unwrap_or_default();
+            for row in 0..row_count {
+                if slot(row, column).is_some() && slot(row, column) == slot(row, column + 1) {
+                    continue;
+                }
+                lines.push(Line {
+                    style,
+                    x: column_bounds[column + 1] - line_width / 2.0,
+                    y: row_bounds[row],
+                    width: line_width,
+                    height: row_bounds[row + 1] - row_bounds[row],
+                });
+            }
+        }
+        for row in 0..row_count.saturating_sub(1) {
+            let style = nth_or_last(&self.rowlines, row).unwrap_or_default();
+            for column in 0..column_count {
+                if slot(row, column).is_some() && slot(row, column) == slot(row + 1, column) {
+                    continue;
+                }
+                lines.push(Line {
+                    style,
+                    x: column_bounds[column],
+                    y: row_bounds[row + 1] - line_width / 2.0,
+                    width: column_bounds[column + 1] - column_bounds[column],
+                    height: line_width,
+                });
+            }
+        }
+        if framed {
+            let frame = |x, y, width, height| Line {
+                style: self.frame,
+                x,
+                y,
+                width,
+                height,
+            };
+            lines.extend([
+                frame(0.0, 0.0, width, line_width),
+                frame(0.0, height - line_width, width, line_width),
+                frame(0.0, 0.0, line_width, height),
+                frame(width - line_width, 0.0, line_width, height),
+            ]);
+        }
+
+        let color = style.color;
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            for (plan, cell_x, cell_y) in &cells {
+                plan.draw(canvas, x + cell_x, y + cell_y);
+            }
+            for line in &lines {
+                line.draw(canvas, x, y, line_width, color);
+            }
+        };
+
+        // The middle of the table is on the math axis
+        let ascent = height / 2.0 + axis_height;
+        Ok(RenderingPlan {
+            callback: Box::new(callback),
+            ascent,
+            descent: height - ascent,
+            width,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mml_types::{
+        Element, Mtd, Mtr, mi, mn, mo, mrow, mtable, mtd, munderover, stretchy_mo,
+    };
+    use crate::test_fonts::math_font_renderer;
+    use function_name::named;
+
+    #[named]
+    #[test]
+    fn matrix() {
+        let Element::Mtable(mut table) = mtable(vec![
+            vec![mi("a"), mrow(vec![mi("b"), mo("+"), mn("1")])],
+            vec![mrow(vec![mn("2"), mi("c")]), mi("d")],
+        ]) else {
+            unreachable!();
+        };
+        table.columnlines = vec![LineStyle::Dashed];
+        let whole = mrow(vec![
+            stretchy_mo("("),
+            Element::Mtable(table),
+            stretchy_mo(")"),
+        ]);
+        let font_size = 100.0;
+
+        let img = whole.render(&mut TextRenderer::new(), &StyleContext::new(font_size));
+
+        img.save_png(format!("examples/{}.png", function_name!()))
+            .unwrap();
+    }
+
+    #[test]
+    fn column_widths() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        let axis_height = text_renderer.math_constants().axis_height * font_size;
+        let x_height = text_renderer.x_height() * font_size;
+        let spacing = DEFAULT_COLUMN_SPACING.to_px(font_size, x_height);
+        let narrow = mn("1").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let wide = mn("100").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+
+        let table = mtable(vec![vec![mn("1"), mn("1")], vec![mn("100"), mn("1")]])
+            .plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!((table.width - (wide.width + spacing + narrow.width)).abs() < 0.01);
+        assert!((table.ascent - table.descent - 2.0 * axis_height).abs() < 0.01);
+
+        // A cell spanning both columns shares its extra width between them
+        let Element::Mtable(mut spanning) =
+            mtable(vec![vec![mn("100000000")], vec![mn("1"), mn("1")]])
+        else {
+            unreachable!();
+        };
+        spanning.rows[0].cells[0].columnspan = 2;
+        spanning.columnalign = vec![ColumnAlign::Left];
+        let longest =
+            mn("100000000").plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let spanning = spanning.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!((spanning.width - longest.width).abs() < 0.01);
+    }
+
+    #[test]
+    fn inline_cells() {
+        let mut text_renderer = TextRenderer::new();
+        let font_size = 100.0;
+        let sum = || mtable(vec![vec![munderover(mo("∑"), mi("i"), mi("n"))]]);
+
+        // The limits of the sum are scripts in a display formula too
+        let inline = sum().plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let display = sum().plan_render(
+            &mut text_renderer,
+            &StyleContext {
+                displaystyle: true,
+                ..StyleContext::new(font_size)
+            },
+        );
+        assert_eq!(display.width, inline.width);
+        assert_eq!(display.height(), inline.height());
+    }
+
+    #[test]
+    fn row_spans() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        // The second row starts after the cell spanning both rows
+        let table = Mtable {
+            rows: vec![
+                Mtr {
+                    cells: vec![
+                        Mtd {
+                            rowspan: 2,
+                            ..mtd(mi("x"))
+                        },
+                        mtd(mn("1")),
+                    ],
+                    ..Default::default()
+                },
+                Mtr {
+                    cells: vec![mtd(mn("2"))],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let two_columns = mtable(vec![vec![mi("x"), mn("1")], vec![mi("x"), mn("2")]]);
+        let table = table.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        let two_columns =
+            two_columns.plan_render(&mut text_renderer, &StyleContext::new(font_size));
+        assert!((table.width - two_columns.width).abs() < 0.01);
+        assert!((table.height() - two_columns.height()).abs() < 0.01);
+    }
+
+    #[test]
+    fn lines() {
+        let mut text_renderer = math_font_renderer();
+        let font_size = 100.0;
+        let rects = |table: Mtable, text_renderer: &mut TextRenderer| {
+            table
+                .render_svg(text_renderer, &StyleContext::new(font_size))
+                .matches("<rect")
+                .count()
+        };
+        let table = || {
+            let Element::Mtable(table) =
+                mtable(vec![vec![mi("a"), mi("b")], vec![mi("c"), mi("d")]])
+            else {
+                unreachable!();
+            };
+            table
+        };
+
+        assert_eq!(rects(table(), &mut text_renderer), 0);
+        // One segment per row or column crossed
+        let solid = Mtable {
+            rowlines: vec![LineStyle::Solid],
+            columnlines: vec![LineStyle::Solid],
+            ..table()
+        };
+        assert_eq!(rects(solid, &mut text_renderer), 4);
+        let mut spanning = Mtable {
+            rowlines: vec![LineStyle::Solid],
+            columnlines: vec![LineStyle::Solid],
+            ..table()
+        };
+        spanning.rows[0].cells.pop();
+        spanning.rows[0].cells[0].columnspan = 2;
+        assert_eq!(rects(spanning, &mut text_renderer), 3);
+        let framed = Mtable {
+            frame: LineStyle::Solid,
+            ..table()
+        };
+        assert_eq!(rects(framed, &mut text_renderer), 4);
+        let dashed = Mtable {
+            frame: LineStyle::Dashed,
+            ..table()
+        };
+        assert!(rects(dashed, &mut text_renderer) > 4);
+    }
+}
@@ -0,0 +1,20 @@
+//! Math font of the tests which depend on the layout parameters of a real math font.
+//!
+//! DejaVu Math TeX Gyre is vendored in `tests/fonts` (under the license next to it), so that these
+//! tests run the same on every system.
+
+use crate::text_rendering::TextRenderer;
+
+/// Path of the math font file
+pub const MATH_FONT_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fonts/DejaVuMathTeXGyre.ttf"
+);
+
+/// Content of the math font file
+pub const MATH_FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuMathTeXGyre.ttf");
+
+/// Renderer using the math font
+pub fn math_font_renderer() -> TextRenderer {
+    TextRenderer::with_font_data(MATH_FONT.to_vec()).expect("the math font should load")
+}
@@ -1,23 +1,22 @@
 // Copyright 2024 the Parley Authors
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
-//! A simple example that lays out some text using Parley, extracts outlines using Skrifa and
-//! then paints those outlines using Tiny-Skia.
+//! Lays out text using Parley into glyph runs, which are painted on a [`MathCanvas`] using
+//! outlines extracted by Skrifa.
 //!
 //! Note: Emoji rendering is not currently implemented in this example. See the swash example
 //! if you need emoji rendering.
 
-use crate::render::RenderingPlan;
+use crate::canvas::{self, Color, MathCanvas, PathCommand, PositionedGlyph};
+use crate::math_table::{MathConstants, MathGlyphInfo, MathVariants};
+use crate::render::{RenderError, RenderingPlan};
+use crate::style::StyleContext;
 use parley::{
-    FontContext, FontFamily, GlyphRun, Layout, LayoutContext, PositionedLayoutItem, StyleProperty,
+    Font, FontContext, FontFamily, Layout, LayoutContext, PositionedLayoutItem, StyleProperty,
 };
-use skrifa::{
-    GlyphId, MetadataProvider, OutlineGlyph,
-    instance::{LocationRef, NormalizedCoord, Size},
-    outline::{DrawSettings, OutlinePen},
-    raw::FontRef as ReadFontsRef,
-};
-use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, PixmapMut, Transform};
+use skrifa::{FontRef, MetadataProvider, instance::NormalizedCoord};
+use std::path::Path;
+use tiny_skia::Pixmap;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 struct ColorBrush {
@@ -32,89 +31,243 @@ impl Default for ColorBrush {
     }
 }
 
+const FONT_FAMILY: &str = "TexMaths Symbols";
+/// Height of the small letters in ems, when it can't be measured
+const DEFAULT_X_HEIGHT: f32 = 0.5;
+
 pub struct TextRenderer {
     font_cx: FontContext,
     layout_cx: LayoutContext<ColorBrush>,
+    font_family: String,
+    /// Font of the family used for layout parameters and stretchy glyphs, if it could be loaded
+    math_font: Option<Font>,
+    math_constants: MathConstants,
+    math_variants: MathVariants,
+    math_glyph_info: MathGlyphInfo,
+    /// Height of the small letters in ems, the unit of `ex` lengths
+    x_height: f32,
+}
+
+impl Default for TextRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TextRenderer {
+    /// Renderer using the system font "TexMaths Symbols", or a fallback font if it isn't installed
     pub fn new() -> Self {
-        // Create a FontContext, LayoutContext
-        //
-        // These are both intended to be constructed rarely (perhaps even once per app (or once per thread))
-        // and provide caches and scratch space to avoid allocations
-        let font_cx = FontContext::new();
+        Self::with_font_context(FontContext::new(), FONT_FAMILY.into())
+    }
+
+    /// Renderer using a font family of the font context, whose math font is loaded once
+    fn with_font_context(font_cx: FontContext, font_family: String) -> Self {
+        // The FontContext and LayoutContext are both intended to be constructed rarely (perhaps
+        // even once per app (or once per thread)) and provide caches and scratch space to avoid
+        // allocations
         let layout_cx = LayoutContext::new();
-        Self { font_cx, layout_cx }
+        let mut text_renderer = Self {
+            font_cx,
+            layout_cx,
+            font_family,
+            math_font: None,
+            math_constants: MathConstants::default(),
+            math_variants: MathVariants::default(),
+            math_glyph_info: MathGlyphInfo::default(),
+            x_height: DEFAULT_X_HEIGHT,
+        };
+        text_renderer.load_math_font();
+        text_renderer
     }
-    pub fn render_text(&mut self, text: String, font_size: f32) -> Pixmap {
-        // The display scale for HiDPI rendering
-        let display_scale = 1.0;
 
-        // Create a RangedBuilder
-        let mut builder = self
-            .layout_cx
-            .ranged_builder(&mut self.font_cx, &text, display_scale);
-        // let mut builder = layout_cx.ranged_builder(font_cx, &text, display_scale);
+    /// Renderer using a font from its data (e.g. the content of an .otf file), which doesn't have
+    /// to be installed on the system
+    pub fn with_font_data(data: Vec<u8>) -> Result<Self, RenderError> {
+        let mut font_cx = FontContext::new();
+        let family = register_fonts(&mut font_cx, data)?;
+        Ok(Self::with_font_context(font_cx, family))
+    }
 
-        // Set default text colour styles (set foreground text color)
-        builder.push_default(StyleProperty::Brush(ColorBrush {
-            color: Color::BLACK,
-        }));
+    /// Renderer using the font of a file, which doesn't have to be installed on the system
+    pub fn with_font_path(path: impl AsRef<Path>) -> Result<Self, RenderError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|error| RenderError::FontFile {
+            path: path.to_owned(),
+            error,
+        })?;
+        Self::with_font_data(data)
+    }
 
-        // Set default font family
-        // builder.push_default(StyleProperty::FontStyle(parley::FontStyle::Italic));
-        // builder.push_default(GenericFamily::Math);
-        // builder.push_default(FontFamily::parse("TeX Gyre Pagella Math").unwrap());
-        // builder.push_default(FontFamily::parse("Libertinus Math").unwrap());
-        // builder.push_default(FontFamily::parse("Tex Gyre Termes Math").unwrap());
-        builder.push_default(FontFamily::parse("TexMaths Symbols").unwrap());
-        builder.push_default(StyleProperty::FontSize(font_size));
+    /// Use another font family, either installed on the system or registered with
+    /// [`TextRenderer::register_font`]
+    pub fn with_font_family(mut self, family: &str) -> Result<Self, RenderError> {
+        let Some(FontFamily::Named(name)) = FontFamily::parse(family) else {
+            return Err(RenderError::InvalidFontFamily(family.into()));
+        };
+        if self.font_cx.collection.family_by_name(&name).is_none() {
+            return Err(RenderError::FontNotFound(name.into()));
+        }
+        self.font_family = name.into();
+        self.load_math_font();
+        Ok(self)
+    }
 
-        // Build the builder into a Layout
-        let mut layout: Layout<ColorBrush> = builder.build(&text);
+    /// Register the fonts contained in the data, and return the family name of the first one
+    pub fn register_font(&mut self, data: Vec<u8>) -> Result<String, RenderError> {
+        register_fonts(&mut self.font_cx, data)
+    }
 
-        // Perform layout (including bidi resolution and shaping) with start alignment
-        layout.break_all_lines(None);
-        let width = layout.width().ceil() as u32;
-        let height = layout.height().ceil() as u32;
+    /// Name of the font family used to render text
+    pub fn font_family(&self) -> &str {
+        &self.font_family
+    }
+
+    /// Layout constants of the math font, or fallback values if it has no MATH table (except for
+    /// the axis height, which is measured on the font)
+    pub fn math_constants(&self) -> &MathConstants {
+        &self.math_constants
+    }
+
+    /// Glyph constructions of the math font, empty if it has no MATH table
+    pub fn math_variants(&self) -> &MathVariants {
+        &self.math_variants
+    }
+
+    /// Height of the small letters of the font in ems, which `ex` lengths are relative to
+    pub fn x_height(&self) -> f32 {
+        self.x_height
+    }
+
+    /// Italic corrections and kerning of the glyphs of the math font, empty if it has no MATH
+    /// table
+    pub fn math_glyph_info(&self) -> &MathGlyphInfo {
+        &self.math_glyph_info
+    }
+
+    /// The math font, `None` if no font of the family could be loaded
+    pub fn math_font(&self) -> Option<&Font> {
+        self.math_font.as_ref()
+    }
+
+    /// Glyph of a character in the math font
+    pub fn math_glyph_id(&self, character: char) -> Option<u32> {
+        let font = self.math_font.as_ref()?;
+        let font_ref = FontRef::from_index(font.data.as_ref(), font.index).ok()?;
+        Some(font_ref.charmap().map(character)?.to_u32())
+    }
+
+    /// Load the font of the family along with its MATH table, falling back to default constants
+    /// and an axis measured on the font, and measure its x-height
+    fn load_math_font(&mut self) {
+        self.math_font = self
+            .font_cx
+            .collection
+            .family_by_name(&self.font_family)
+            .and_then(|family| family.default_font().cloned())
+            .and_then(|font| {
+                let data = font.load(Some(&mut self.font_cx.source_cache))?;
+                Some(Font::new(data, font.index()))
+            });
+        let font_ref = self
+            .math_font
+            .as_ref()
+            .and_then(|font| FontRef::from_index(font.data.as_ref(), font.index).ok());
+        let math_constants = font_ref.as_ref().and_then(MathConstants::from_font);
+        self.math_variants = font_ref
+            .as_ref()
+            .and_then(MathVariants::from_font)
+            .unwrap_or_default();
+        self.math_glyph_info = font_ref
+            .as_ref()
+            .and_then(MathGlyphInfo::from_font)
+            .unwrap_or_default();
+        // The math axis of a font without a MATH table is the middle of the plus sign, which
+        // fraction bars and the middle of tables and fences line up with
+        self.math_constants = match math_constants {
+            Some(constants) => constants,
+            None => MathConstants {
+                axis_height: self
+                    .measure_ink("+")
+                    .map_or(MathConstants::default().axis_height, |(top, bottom)| {
+                        -(top + bottom) / 2.0
+                    }),
+                ..MathConstants::default()
+            },
+        };
+        self.x_height = self
+            .measure_ink("x")
+            .map_or(DEFAULT_X_HEIGHT, |(top, _)| -top);
+    }
+
+    /// Top and bottom (with y pointing down) in ems of the inked parts of text shaped with the
+    /// font, `None` if nothing is inked
+    fn measure_ink(&mut self, text: &str) -> Option<(f32, f32)> {
+        const FONT_SIZE: f32 = 100.0;
+        let (glyph_runs, _) = self.shape_text(text, FONT_SIZE).ok()?;
+        let (top, bottom) = ink_bounds(&glyph_runs)?;
+        Some((top / FONT_SIZE, bottom / FONT_SIZE))
+    }
+
+    /// Render text on a white background, in a pixmap fitting its advance width and inked height
+    pub fn render_text(&mut self, text: String, font_size: f32) -> Result<Pixmap, RenderError> {
+        let (glyph_runs, width) = self.shape_text(&text, font_size)?;
+        let (ascent, descent) = ink_extents(&glyph_runs);
+        let width = width.ceil() as u32;
+        let height = (ascent + descent).ceil() as u32;
 
         // Create TinySkia Pixmap
-        let mut img = Pixmap::new(width, height).unwrap();
+        let mut img =
+            Pixmap::new(width, height).ok_or(RenderError::EmptyCanvas { width, height })?;
 
         // Fill background color
-        img.fill(Color::WHITE);
-
-        // Wrap Pixmap in a type that implements skrifa::OutlinePen
-        let mut pen = TinySkiaPen::new(img.as_mut());
+        img.fill(tiny_skia::Color::WHITE);
 
         // Render each glyph run
-        for line in layout.lines() {
-            for item in line.items() {
-                match item {
-                    PositionedLayoutItem::GlyphRun(glyph_run) => {
-                        render_glyph_run(&glyph_run, &mut pen, 0, 0, 0);
-                    }
-                    // PositionedLayoutItem::InlineBox(inline_box) => {
-                    //     pen.set_origin(inline_box.x, inline_box.y);
-                    //     pen.set_color(foreground_color);
-                    //     pen.fill_rect(inline_box.width, inline_box.height);
-                    // }
-                    _ => {}
-                }
-            }
+        for glyph_run in &glyph_runs {
+            img.draw_glyph_run(glyph_run, 0.0, ascent);
         }
-        img
+        Ok(img)
+    }
+    /// Plan text on a single line, in the size and color of the style
+    pub fn plan_render_text(
+        &mut self,
+        text: String,
+        style: &StyleContext,
+    ) -> Result<RenderingPlan, RenderError> {
+        let (mut glyph_runs, width) = self.shape_text(&text, style.font_size)?;
+        for glyph_run in &mut glyph_runs {
+            glyph_run.color = style.color;
+        }
+        let (ascent, descent) = ink_extents(&glyph_runs);
+
+        let callback = move |canvas: &mut dyn MathCanvas, x: f32, y: f32| {
+            for glyph_run in &glyph_runs {
+                canvas.draw_glyph_run(glyph_run, x, y + ascent);
+            }
+        };
+
+        Ok(RenderingPlan {
+            callback: Box::new(callback),
+            ascent,
+            descent,
+            width,
+        })
     }
-    pub fn plan_render_text(&mut self, text: String, font_size: f32) -> RenderingPlan {
+
+    /// Shape text on a single line into glyph runs with their origin on the baseline, along with
+    /// the advance width of the line
+    pub fn shape_text(
+        &mut self,
+        text: &str,
+        font_size: f32,
+    ) -> Result<(Vec<canvas::GlyphRun>, f32), RenderError> {
         // The display scale for HiDPI rendering
         let display_scale = 1.0;
 
         // Create a RangedBuilder
         let mut builder = self
             .layout_cx
-            .ranged_builder(&mut self.font_cx, &text, display_scale);
-        // let mut builder = layout_cx.ranged_builder(font_cx, &text, display_scale);
+            .ranged_builder(&mut self.font_cx, text, display_scale);
 
         // Set default text colour styles (set foreground text color)
         builder.push_default(StyleProperty::Brush(ColorBrush {
@@ -122,288 +275,174 @@ impl TextRenderer {
         }));
 
         // Set default font family
-        // builder.push_default(StyleProperty::FontStyle(parley::FontStyle::Italic));
-        // builder.push_default(GenericFamily::Math);
-        // builder.push_default(FontFamily::parse("TeX Gyre Pagella Math").unwrap());
-        // builder.push_default(FontFamily::parse("Libertinus Math").unwrap());
-        // builder.push_default(FontFamily::parse("Tex Gyre Termes Math").unwrap());
-        builder.push_default(FontFamily::parse("TexMaths Symbols").unwrap());
+        let font_family = FontFamily::parse(&self.font_family)
+            .ok_or_else(|| RenderError::InvalidFontFamily(self.font_family.clone()))?;
+        builder.push_default(font_family);
         builder.push_default(StyleProperty::FontSize(font_size));
 
         // Build the builder into a Layout
-        let mut layout: Layout<ColorBrush> = builder.build(&text);
+        let mut layout: Layout<ColorBrush> = builder.build(text);
 
         // Perform layout (including bidi resolution and shaping) with start alignment
         layout.break_all_lines(None);
-        let width = layout.width().ceil() as u32;
-        let height = layout.height().ceil() as u32;
-        let baseline = height / 2;
-
-        let callback = move |text_rendered: &mut TextRenderer, img: &mut Pixmap, x: u32, y: u32| {
-            // Fill background color
-            // img.fill(Color::WHITE);
-
-            // Wrap Pixmap in a type that implements skrifa::OutlinePen
-            let mut pen = TinySkiaPen::new(img.as_mut());
-
-            // Render each glyph run
-            for line in layout.lines() {
-                for item in line.items() {
-                    match item {
-                        PositionedLayoutItem::GlyphRun(glyph_run) => {
-                            render_glyph_run(&glyph_run, &mut pen, x, y, 0);
-                        }
-                        // PositionedLayoutItem::InlineBox(inline_box) => {
-                        //     pen.set_origin(inline_box.x, inline_box.y);
-                        //     pen.set_color(foreground_color);
-                        //     pen.fill_rect(inline_box.width, inline_box.height);
-                        // }
-                        _ => {}
-                    }
-                }
-            }
-        };
-
-        RenderingPlan {
-            callback: Box::new(callback),
-            /// vertical position on the element where the horizontal line of the '+' symbol should be aligned with
-            baseline,
-            width,
-            height,
+        let mut glyph_runs = glyph_runs(&layout);
+        let baseline = layout_baseline(&layout);
+        for glyph in glyph_runs.iter_mut().flat_map(|run| &mut run.glyphs) {
+            glyph.y -= baseline;
         }
+        Ok((glyph_runs, layout.width()))
     }
 }
 
-pub fn render_text(text: String, font_size: f32) -> Pixmap {
-    // The display scale for HiDPI rendering
-    let display_scale = 1.0;
-
-    // Colours for rendering
-    let foreground_color = Color::BLACK;
-    let background_color = Color::WHITE;
-
-    // Create a FontContext, LayoutContext
-    //
-    // These are both intended to be constructed rarely (perhaps even once per app (or once per thread))
-    // and provide caches and scratch space to avoid allocations
-    let mut font_cx = FontContext::new();
-    let mut layout_cx = LayoutContext::new();
-
-    // Create a RangedBuilder
-    let mut builder = layout_cx.ranged_builder(&mut font_cx, &text, display_scale);
-
-    // Set default text colour styles (set foreground text color)
-    let foreground_brush = ColorBrush {
-        color: Color::BLACK,
-    };
-    let brush_style = StyleProperty::Brush(foreground_brush);
-    builder.push_default(brush_style);
-
-    // Set default font family
-    // builder.push_default(StyleProperty::FontStyle(parley::FontStyle::Italic));
-    // builder.push_default(GenericFamily::Math);
-    // builder.push_default(FontFamily::parse("TeX Gyre Pagella Math").unwrap());
-    // builder.push_default(FontFamily::parse("Libertinus Math").unwrap());
-    // builder.push_default(FontFamily::parse("Tex Gyre Termes Math").unwrap());
-    builder.push_default(FontFamily::parse("TexMaths Symbols").unwrap());
-    builder.push_default(StyleProperty::FontSize(font_size));
-
-    // Build the builder into a Layout
-    let mut layout: Layout<ColorBrush> = builder.build(&text);
-
-    // Perform layout (including bidi resolution and shaping) with start alignment
-    layout.break_all_lines(None);
-    let width = layout.width().ceil() as u32;
-    let height = layout.height().ceil() as u32;
-
-    // Create TinySkia Pixmap
-    let mut img = Pixmap::new(width, height).unwrap();
-
-    // Fill background color
-    img.fill(background_color);
-
-    // Wrap Pixmap in a type that implements skrifa::OutlinePen
-    let mut pen = TinySkiaPen::new(img.as_mut());
-
-    // Render each glyph run
+/// Register the fonts contained in the data, and return the family name of the first one
+fn register_fonts(font_cx: &mut FontContext, data: Vec<u8>) -> Result<String, RenderError> {
+    let families = font_cx.collection.register_fonts(data);
+    families
+        .first()
+        .and_then(|(id, _)| font_cx.collection.family_name(*id))
+        .map(String::from)
+        .ok_or(RenderError::InvalidFontData)
+}
+
+/// Extract the glyph runs of a layout, positioned relative to the top left of the layout
+fn glyph_runs(layout: &Layout<ColorBrush>) -> Vec<canvas::GlyphRun> {
+    let mut glyph_runs = vec![];
     for line in layout.lines() {
         for item in line.items() {
-            match item {
-                PositionedLayoutItem::GlyphRun(glyph_run) => {
-                    render_glyph_run(&glyph_run, &mut pen, 0, 0, 0);
-                }
-                // PositionedLayoutItem::InlineBox(inline_box) => {
-                //     pen.set_origin(inline_box.x, inline_box.y);
-                //     pen.set_color(foreground_color);
-                //     pen.fill_rect(inline_box.width, inline_box.height);
-                // }
-                _ => {}
-            }
+            // PositionedLayoutItem::InlineBox(inline_box) is not used
+            let PositionedLayoutItem::GlyphRun(glyph_run) = item else {
+                continue;
+            };
+            // Resolve properties of the GlyphRun
+            let mut run_x = glyph_run.offset();
+            let run_y = glyph_run.baseline();
+            let style = glyph_run.style();
+
+            // Get the "Run" from the "GlyphRun"
+            let run = glyph_run.run();
+
+            let glyphs = glyph_run
+                .glyphs()
+                .map(|glyph| {
+                    let positioned_glyph = PositionedGlyph {
+                        id: glyph.id.into(),
+                        x: run_x + glyph.x,
+                        y: run_y - glyph.y,
+                    };
+                    run_x += glyph.advance;
+                    positioned_glyph
+                })
+                .collect();
+
+            glyph_runs.push(canvas::GlyphRun {
+                font: run.font().clone(),
+                font_size: run.font_size(),
+                normalized_coords: run
+                    .normalized_coords()
+                    .iter()
+                    .map(|coord| NormalizedCoord::from_bits(*coord))
+                    .collect(),
+                glyphs,
+                color: style.brush.color,
+            });
         }
     }
-    img
+    glyph_runs
 }
 
-fn render_glyph_run(
-    glyph_run: &GlyphRun<'_, ColorBrush>,
-    pen: &mut TinySkiaPen<'_>,
-    offset_x: u32,
-    offset_y: u32,
-    padding: u32,
-) {
-    // Resolve properties of the GlyphRun
-    let mut run_x = glyph_run.offset();
-    let run_y = glyph_run.baseline();
-    let style = glyph_run.style();
-    let brush = style.brush;
-
-    // Get the "Run" from the "GlyphRun"
-    let run = glyph_run.run();
-
-    // Resolve properties of the Run
-    let font = run.font();
-    let font_size = run.font_size();
-
-    let normalized_coords = run
-        .normalized_coords()
-        .iter()
-        .map(|coord| NormalizedCoord::from_bits(*coord))
-        .collect::<Vec<_>>();
-
-    // Get glyph outlines using Skrifa. This can be cached in production code.
-    let font_collection_ref = font.data.as_ref();
-    let font_ref = ReadFontsRef::from_index(font_collection_ref, font.index).unwrap();
-    let outlines = font_ref.outline_glyphs();
-
-    // Iterates over the glyphs in the GlyphRun
-    for glyph in glyph_run.glyphs() {
-        let glyph_x = run_x + glyph.x + padding as f32 + offset_x as f32;
-        let glyph_y = run_y - glyph.y + padding as f32 + offset_y as f32;
-        run_x += glyph.advance;
-
-        let glyph_id = GlyphId::from(glyph.id);
-        if let Some(glyph_outline) = outlines.get(glyph_id) {
-            pen.set_origin(glyph_x, glyph_y);
-            pen.set_color(brush.color);
-            pen.draw_glyph(&glyph_outline, font_size, &normalized_coords);
-        }
-    }
-
-    // Draw decorations: underline & strikethrough
-    // let style = glyph_run.style();
-    // let run_metrics = run.metrics();
-    // if let Some(decoration) = &style.underline {
-    //     let offset = decoration.offset.unwrap_or(run_metrics.underline_offset);
-    //     let size = decoration.size.unwrap_or(run_metrics.underline_size);
-    //     render_decoration(pen, glyph_run, decoration.brush, offset, size, padding);
-    // }
-    // if let Some(decoration) = &style.strikethrough {
-    //     let offset = decoration
-    //         .offset
-    //         .unwrap_or(run_metrics.strikethrough_offset);
-    //     let size = decoration.size.unwrap_or(run_metrics.strikethrough_size);
-    //     render_decoration(pen, glyph_run, decoration.brush, offset, size, padding);
-    // }
+/// Position of the baseline of the first line, relative to the top of the layout
+fn layout_baseline(layout: &Layout<ColorBrush>) -> f32 {
+    layout
+        .lines()
+        .next()
+        .map(|line| line.metrics().baseline)
+        .unwrap_or(0.0)
 }
 
-// fn render_decoration(
-//     pen: &mut TinySkiaPen<'_>,
-//     glyph_run: &GlyphRun<'_, ColorBrush>,
-//     brush: ColorBrush,
-//     offset: f32,
-//     width: f32,
-//     padding: u32,
-// ) {
-//     let y = glyph_run.baseline() - offset + padding as f32;
-//     let x = glyph_run.offset() + padding as f32;
-//     pen.set_color(brush.color);
-//     pen.set_origin(x, y);
-//     pen.fill_rect(glyph_run.advance(), width);
-// }
-
-struct TinySkiaPen<'a> {
-    pub(crate) pixmap: PixmapMut<'a>,
-    pub(crate) x: f32,
-    pub(crate) y: f32,
-    pub(crate) paint: Paint<'static>,
-    pub(crate) open_path: PathBuilder,
+/// Height of the inked parts of the glyphs above and below the baseline of the runs' origin
+pub(crate) fn ink_extents(glyph_runs: &[canvas::GlyphRun]) -> (f32, f32) {
+    let (top, bottom) = ink_bounds(glyph_runs).unwrap_or((0.0, 0.0));
+    (-top.min(0.0), bottom.max(0.0))
 }
 
-impl TinySkiaPen<'_> {
-    pub(crate) fn new(pixmap: PixmapMut<'_>) -> TinySkiaPen<'_> {
-        TinySkiaPen {
-            pixmap,
-            x: 0.0,
-            y: 0.0,
-            paint: Paint::default(),
-            open_path: PathBuilder::new(),
-        }
-    }
-
-    pub(crate) fn set_origin(&mut self, x: f32, y: f32) {
-        self.x = x;
-        self.y = y;
-    }
-
-    pub(crate) fn set_color(&mut self, color: Color) {
-        self.paint.set_color(color);
-    }
-
-    // pub(crate) fn fill_rect(&mut self, width: f32, height: f32) {
-    //     let rect = Rect::from_xywh(self.x, self.y, width, height).unwrap();
-    //     self.pixmap
-    //         .fill_rect(rect, &self.paint, Transform::identity(), None);
-    // }
-
-    pub(crate) fn draw_glyph(
-        &mut self,
-        glyph: &OutlineGlyph<'_>,
-        size: f32,
-        normalized_coords: &[NormalizedCoord],
-    ) {
-        let location_ref = LocationRef::new(normalized_coords);
-        let settings = DrawSettings::unhinted(Size::new(size), location_ref);
-        glyph.draw(settings, self).unwrap();
-
-        let builder = core::mem::replace(&mut self.open_path, PathBuilder::new());
-        if let Some(path) = builder.finish() {
-            self.pixmap.fill_path(
-                &path,
-                &self.paint,
-                FillRule::Winding,
-                Transform::identity(),
-                None,
-            );
+/// Lowest and highest y (pointing down) of the inked parts of the glyphs, relative to the runs'
+/// origin, `None` if nothing is inked
+fn ink_bounds(glyph_runs: &[canvas::GlyphRun]) -> Option<(f32, f32)> {
+    let (mut top, mut bottom) = (f32::INFINITY, f32::NEG_INFINITY);
+    for glyph_run in glyph_runs {
+        for glyph in &glyph_run.glyphs {
+            let Some(path) = glyph_run.glyph_path(glyph, 0.0, 0.0) else {
+                continue;
+            };
+            // Control points are included, so the extents are slightly overestimated for curves
+            for command in path.commands() {
+                let ys = match *command {
+                    PathCommand::MoveTo(_, y) | PathCommand::LineTo(_, y) => [y, y, y],
+                    PathCommand::QuadTo(_, cy, _, y) => [cy, y, y],
+                    PathCommand::CurveTo(_, cy0, _, cy1, _, y) => [cy0, cy1, y],
+                    PathCommand::Close => continue,
+                };
+                for y in ys {
+                    top = top.min(y);
+                    bottom = bottom.max(y);
+                }
+            }
         }
     }
+    (top <= bottom).then_some((top, bottom))
 }
 
-impl OutlinePen for TinySkiaPen<'_> {
-    fn move_to(&mut self, x: f32, y: f32) {
-        self.open_path.move_to(self.x + x, self.y - y);
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{MATH_FONT_PATH, math_font_renderer};
 
-    fn line_to(&mut self, x: f32, y: f32) {
-        self.open_path.line_to(self.x + x, self.y - y);
+    #[test]
+    fn font_from_path() {
+        let text_renderer = TextRenderer::with_font_path(MATH_FONT_PATH).unwrap();
+        assert_eq!(text_renderer.font_family(), "DejaVu Math TeX Gyre");
+        assert_ne!(text_renderer.math_constants(), &MathConstants::default());
     }
 
-    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
-        self.open_path
-            .quad_to(self.x + cx0, self.y - cy0, self.x + x, self.y - y);
+    #[test]
+    fn text_pixmap() {
+        let mut text_renderer = math_font_renderer();
+        let plan = text_renderer
+            .plan_render_text("x".into(), &StyleContext::new(100.0))
+            .unwrap();
+        let img = text_renderer.render_text("x".into(), 100.0).unwrap();
+        assert_eq!(img.width(), plan.width.ceil() as u32);
+        assert_eq!(img.height(), plan.height().ceil() as u32);
+        assert!(matches!(
+            text_renderer.render_text(String::new(), 100.0),
+            Err(RenderError::EmptyCanvas { .. })
+        ));
     }
 
-    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
-        self.open_path.cubic_to(
-            self.x + cx0,
-            self.y - cy0,
-            self.x + cx1,
-            self.y - cy1,
-            self.x + x,
-            self.y - y,
-        );
+    #[test]
+    fn missing_fonts() {
+        assert!(matches!(
+            TextRenderer::with_font_path("/nonexistent/font.otf"),
+            Err(RenderError::FontFile { .. })
+        ));
+        assert!(matches!(
+            TextRenderer::with_font_data(b"not a font".to_vec()),
+            Err(RenderError::InvalidFontData)
+        ));
+        assert!(matches!(
+            TextRenderer::new().with_font_family("Nonexistent Math"),
+            Err(RenderError::FontNotFound(family)) if family == "Nonexistent Math"
+        ));
     }
 
-    fn close(&mut self) {
-        self.open_path.close();
+    #[test]
+    fn axis_through_plus_sign() {
+        for mut text_renderer in [TextRenderer::new(), math_font_renderer()] {
+            let font_size = 100.0;
+            let axis_height = text_renderer.math_constants().axis_height * font_size;
+            let (glyph_runs, _) = text_renderer.shape_text("+", font_size).unwrap();
+            let (top, bottom) = ink_bounds(&glyph_runs).unwrap();
+            assert!((-(top + bottom) / 2.0 - axis_height).abs() < 2.0);
+        }
     }
 }